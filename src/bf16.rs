@@ -1,4 +1,4 @@
-use crate::{Float, RoundingMode, F128, F16, F32, F64};
+use crate::{op, Float, Operation, RoundingMode, Specialization, F128, F16, F32, F64};
 use softfloat_sys::{float16_t, float32_t};
 use std::borrow::Borrow;
 
@@ -25,11 +25,46 @@ fn to_f32(x: float16_t) -> float32_t {
 }
 
 fn from_f32(x: float32_t) -> float16_t {
-    float16_t {
-        v: (x.v >> 16) as u16,
+    // NaN is converted here because f32_to_bf16 shifts the payload by one bit
+    if (x.v & 0x7f80_0000) == 0x7f80_0000 && (x.v & 0x007f_ffff) != 0 {
+        if (x.v & 0x0040_0000) == 0 {
+            unsafe { softfloat_sys::softfloat_raiseFlags(softfloat_sys::softfloat_flag_invalid) };
+        }
+        let nan = F32::from_bits(x.v);
+        let ret: BF16 = Specialization::compiled().convert_nan(&nan);
+        ret.0
+    } else {
+        let ret = unsafe { softfloat_sys::f32_to_bf16(x) };
+        float16_t { v: ret.v }
     }
 }
 
+/// Computes `op` in `float32_t` with round-to-odd and rounds the result to bfloat16 by `rnd`.
+///
+/// `float32_t` has the same exponent range as bfloat16 and 16 more fraction bits,
+/// so rounding to odd first keeps enough information for the final rounding to be correct.
+/// Inexact and underflow are taken from the final rounding only.
+pub(crate) fn round_from_f32<F: Fn() -> float32_t>(op: F, rnd: RoundingMode) -> float16_t {
+    let flags = unsafe { softfloat_sys::softfloat_exceptionFlags_read_helper() };
+    let mut ret = unsafe {
+        softfloat_sys::softfloat_exceptionFlags_write_helper(0);
+        softfloat_sys::softfloat_roundingMode_write_helper(softfloat_sys::softfloat_round_odd);
+        op()
+    };
+    let op_flags = unsafe { softfloat_sys::softfloat_exceptionFlags_read_helper() };
+    let op_flags = op_flags
+        & (softfloat_sys::softfloat_flag_invalid
+            | softfloat_sys::softfloat_flag_infinite
+            | softfloat_sys::softfloat_flag_overflow);
+    rnd.set();
+    if ret.v & 0x7fff_ffff == 0 {
+        // zero results are always exact, but the sign of an exact zero sum depends on `rnd`
+        ret = op();
+    }
+    unsafe { softfloat_sys::softfloat_exceptionFlags_write_helper(flags | op_flags) };
+    from_f32(ret)
+}
+
 impl Float for BF16 {
     type Payload = u16;
 
@@ -59,71 +94,106 @@ impl Float for BF16 {
     }
 
     fn add<T: Borrow<Self>>(&self, x: T, rnd: RoundingMode) -> Self {
-        rnd.set();
-        let ret = unsafe { softfloat_sys::f32_add(to_f32(self.0), to_f32(x.borrow().0)) };
-        Self(from_f32(ret))
+        op::binary(Operation::Add, self, x.borrow(), |a, b| {
+            let ret = round_from_f32(
+                || unsafe { softfloat_sys::f32_add(to_f32(a.0), to_f32(b.0)) },
+                rnd,
+            );
+            Self(ret)
+        })
     }
 
     fn sub<T: Borrow<Self>>(&self, x: T, rnd: RoundingMode) -> Self {
-        rnd.set();
-        let ret = unsafe { softfloat_sys::f32_sub(to_f32(self.0), to_f32(x.borrow().0)) };
-        Self(from_f32(ret))
+        op::binary(Operation::Sub, self, x.borrow(), |a, b| {
+            let ret = round_from_f32(
+                || unsafe { softfloat_sys::f32_sub(to_f32(a.0), to_f32(b.0)) },
+                rnd,
+            );
+            Self(ret)
+        })
     }
 
     fn mul<T: Borrow<Self>>(&self, x: T, rnd: RoundingMode) -> Self {
-        rnd.set();
-        let ret = unsafe { softfloat_sys::f32_mul(to_f32(self.0), to_f32(x.borrow().0)) };
-        Self(from_f32(ret))
+        op::binary(Operation::Mul, self, x.borrow(), |a, b| {
+            let ret = round_from_f32(
+                || unsafe { softfloat_sys::f32_mul(to_f32(a.0), to_f32(b.0)) },
+                rnd,
+            );
+            Self(ret)
+        })
     }
 
     fn fused_mul_add<T: Borrow<Self>>(&self, x: T, y: T, rnd: RoundingMode) -> Self {
-        rnd.set();
-        let ret = unsafe {
-            softfloat_sys::f32_mulAdd(to_f32(self.0), to_f32(x.borrow().0), to_f32(y.borrow().0))
-        };
-        Self(from_f32(ret))
+        op::fused_mul_add(self, x.borrow(), y.borrow(), |a, b, c| {
+            let ret = round_from_f32(
+                || unsafe { softfloat_sys::f32_mulAdd(to_f32(a.0), to_f32(b.0), to_f32(c.0)) },
+                rnd,
+            );
+            Self(ret)
+        })
     }
 
     fn div<T: Borrow<Self>>(&self, x: T, rnd: RoundingMode) -> Self {
-        rnd.set();
-        let ret = unsafe { softfloat_sys::f32_div(to_f32(self.0), to_f32(x.borrow().0)) };
-        Self(from_f32(ret))
+        op::binary(Operation::Div, self, x.borrow(), |a, b| {
+            let ret = round_from_f32(
+                || unsafe { softfloat_sys::f32_div(to_f32(a.0), to_f32(b.0)) },
+                rnd,
+            );
+            Self(ret)
+        })
     }
 
     fn rem<T: Borrow<Self>>(&self, x: T, rnd: RoundingMode) -> Self {
-        rnd.set();
-        let ret = unsafe { softfloat_sys::f32_rem(to_f32(self.0), to_f32(x.borrow().0)) };
-        Self(from_f32(ret))
+        op::binary(Operation::Rem, self, x.borrow(), |a, b| {
+            let ret = round_from_f32(
+                || unsafe { softfloat_sys::f32_rem(to_f32(a.0), to_f32(b.0)) },
+                rnd,
+            );
+            Self(ret)
+        })
     }
 
     fn sqrt(&self, rnd: RoundingMode) -> Self {
-        rnd.set();
-        let ret = unsafe { softfloat_sys::f32_sqrt(to_f32(self.0)) };
-        Self(from_f32(ret))
+        op::unary(Operation::Sqrt, self, |a| {
+            let ret = round_from_f32(|| unsafe { softfloat_sys::f32_sqrt(to_f32(a.0)) }, rnd);
+            Self(ret)
+        })
     }
 
     fn eq<T: Borrow<Self>>(&self, x: T) -> bool {
-        unsafe { softfloat_sys::f32_eq(to_f32(self.0), to_f32(x.borrow().0)) }
+        op::compare(self, x.borrow(), |a, b| unsafe {
+            softfloat_sys::f32_eq(to_f32(a.0), to_f32(b.0))
+        })
     }
 
     fn lt<T: Borrow<Self>>(&self, x: T) -> bool {
-        unsafe { softfloat_sys::f32_lt(to_f32(self.0), to_f32(x.borrow().0)) }
+        op::compare(self, x.borrow(), |a, b| unsafe {
+            softfloat_sys::f32_lt(to_f32(a.0), to_f32(b.0))
+        })
     }
 
     fn le<T: Borrow<Self>>(&self, x: T) -> bool {
-        unsafe { softfloat_sys::f32_le(to_f32(self.0), to_f32(x.borrow().0)) }
+        op::compare(self, x.borrow(), |a, b| unsafe {
+            softfloat_sys::f32_le(to_f32(a.0), to_f32(b.0))
+        })
     }
 
     fn lt_quiet<T: Borrow<Self>>(&self, x: T) -> bool {
-        unsafe { softfloat_sys::f32_lt_quiet(to_f32(self.0), to_f32(x.borrow().0)) }
+        op::compare(self, x.borrow(), |a, b| unsafe {
+            softfloat_sys::f32_lt_quiet(to_f32(a.0), to_f32(b.0))
+        })
     }
 
     fn le_quiet<T: Borrow<Self>>(&self, x: T) -> bool {
-        unsafe { softfloat_sys::f32_le_quiet(to_f32(self.0), to_f32(x.borrow().0)) }
+        op::compare(self, x.borrow(), |a, b| unsafe {
+            softfloat_sys::f32_le_quiet(to_f32(a.0), to_f32(b.0))
+        })
     }
 
     fn eq_signaling<T: Borrow<Self>>(&self, x: T) -> bool {
-        unsafe { softfloat_sys::f32_eq_signaling(to_f32(self.0), to_f32(x.borrow().0)) }
+        op::compare(self, x.borrow(), |a, b| unsafe {
+            softfloat_sys::f32_eq_signaling(to_f32(a.0), to_f32(b.0))
+        })
     }
 
     fn is_signaling_nan(&self) -> bool {
@@ -131,53 +201,67 @@ impl Float for BF16 {
     }
 
     fn from_u32(x: u32, rnd: RoundingMode) -> Self {
-        rnd.set();
-        let ret = unsafe { softfloat_sys::ui32_to_f32(x) };
-        Self(from_f32(ret))
+        op::from_int(x as u128, || {
+            let ret = round_from_f32(|| unsafe { softfloat_sys::ui32_to_f32(x) }, rnd);
+            Self(ret)
+        })
     }
 
     fn from_u64(x: u64, rnd: RoundingMode) -> Self {
-        rnd.set();
-        let ret = unsafe { softfloat_sys::ui64_to_f32(x) };
-        Self(from_f32(ret))
+        op::from_int(x as u128, || {
+            let ret = round_from_f32(|| unsafe { softfloat_sys::ui64_to_f32(x) }, rnd);
+            Self(ret)
+        })
     }
 
     fn from_i32(x: i32, rnd: RoundingMode) -> Self {
-        rnd.set();
-        let ret = unsafe { softfloat_sys::i32_to_f32(x) };
-        Self(from_f32(ret))
+        op::from_int(x as u128, || {
+            let ret = round_from_f32(|| unsafe { softfloat_sys::i32_to_f32(x) }, rnd);
+            Self(ret)
+        })
     }
 
     fn from_i64(x: i64, rnd: RoundingMode) -> Self {
-        rnd.set();
-        let ret = unsafe { softfloat_sys::i64_to_f32(x) };
-        Self(from_f32(ret))
+        op::from_int(x as u128, || {
+            let ret = round_from_f32(|| unsafe { softfloat_sys::i64_to_f32(x) }, rnd);
+            Self(ret)
+        })
     }
 
     fn to_u32(&self, rnd: RoundingMode, exact: bool) -> u32 {
-        let ret = unsafe { softfloat_sys::f32_to_ui32(to_f32(self.0), rnd.to_softfloat(), exact) };
-        ret as u32
+        op::to_int(self, |a| {
+            let ret = unsafe { softfloat_sys::f32_to_ui32(to_f32(a.0), rnd.to_softfloat(), exact) };
+            ret as u32
+        })
     }
 
     fn to_u64(&self, rnd: RoundingMode, exact: bool) -> u64 {
-        let ret = unsafe { softfloat_sys::f32_to_ui64(to_f32(self.0), rnd.to_softfloat(), exact) };
-        ret
+        op::to_int(self, |a| {
+            let ret = unsafe { softfloat_sys::f32_to_ui64(to_f32(a.0), rnd.to_softfloat(), exact) };
+            ret
+        })
     }
 
     fn to_i32(&self, rnd: RoundingMode, exact: bool) -> i32 {
-        let ret = unsafe { softfloat_sys::f32_to_i32(to_f32(self.0), rnd.to_softfloat(), exact) };
-        ret as i32
+        op::to_int(self, |a| {
+            let ret = unsafe { softfloat_sys::f32_to_i32(to_f32(a.0), rnd.to_softfloat(), exact) };
+            ret as i32
+        })
     }
 
     fn to_i64(&self, rnd: RoundingMode, exact: bool) -> i64 {
-        let ret = unsafe { softfloat_sys::f32_to_i64(to_f32(self.0), rnd.to_softfloat(), exact) };
-        ret
+        op::to_int(self, |a| {
+            let ret = unsafe { softfloat_sys::f32_to_i64(to_f32(a.0), rnd.to_softfloat(), exact) };
+            ret
+        })
     }
 
     fn to_f16(&self, rnd: RoundingMode) -> F16 {
-        rnd.set();
-        let ret = unsafe { softfloat_sys::f32_to_f16(to_f32(self.0)) };
-        F16::from_bits(ret.v)
+        op::convert(self, |a| {
+            rnd.set();
+            let ret = unsafe { softfloat_sys::f32_to_f16(to_f32(a.0)) };
+            F16::from_bits(ret.v)
+        })
     }
 
     fn to_bf16(&self, _rnd: RoundingMode) -> BF16 {
@@ -189,24 +273,38 @@ impl Float for BF16 {
     }
 
     fn to_f64(&self, rnd: RoundingMode) -> F64 {
-        rnd.set();
-        let ret = unsafe { softfloat_sys::f32_to_f64(to_f32(self.0)) };
-        F64::from_bits(ret.v)
+        op::convert(self, |a| {
+            rnd.set();
+            let ret = unsafe { softfloat_sys::f32_to_f64(to_f32(a.0)) };
+            F64::from_bits(ret.v)
+        })
     }
 
     fn to_f128(&self, rnd: RoundingMode) -> F128 {
-        rnd.set();
-        let ret = unsafe { softfloat_sys::f32_to_f128(to_f32(self.0)) };
-        let mut v = 0u128;
-        v |= ret.v[0] as u128;
-        v |= (ret.v[1] as u128) << 64;
-        F128::from_bits(v)
+        op::convert(self, |a| {
+            rnd.set();
+            let ret = unsafe { softfloat_sys::f32_to_f128(to_f32(a.0)) };
+            let mut v = 0u128;
+            v |= ret.v[0] as u128;
+            v |= (ret.v[1] as u128) << 64;
+            F128::from_bits(v)
+        })
     }
 
     fn round_to_integral(&self, rnd: RoundingMode) -> Self {
-        let ret =
-            unsafe { softfloat_sys::f32_roundToInt(to_f32(self.0), rnd.to_softfloat(), false) };
-        Self(from_f32(ret))
+        op::unary(Operation::RoundToIntegral, self, |a| {
+            let ret =
+                unsafe { softfloat_sys::f32_roundToInt(to_f32(a.0), rnd.to_softfloat(), false) };
+            Self(from_f32(ret))
+        })
+    }
+
+    fn round_to_integral_exact(&self, rnd: RoundingMode) -> Self {
+        op::unary(Operation::RoundToIntegral, self, |a| {
+            let ret =
+                unsafe { softfloat_sys::f32_roundToInt(to_f32(a.0), rnd.to_softfloat(), true) };
+            Self(from_f32(ret))
+        })
     }
 }
 
@@ -216,6 +314,19 @@ mod tests {
     use crate::ExceptionFlags;
     use std::cmp::Ordering;
 
+    #[derive(Copy, Clone, Debug, Default, PartialEq)]
+    struct BF16Traits;
+
+    impl simple_soft_float::FloatTraits for BF16Traits {
+        type Bits = u16;
+
+        fn properties(&self) -> simple_soft_float::FloatProperties {
+            simple_soft_float::FloatProperties::new(8, 7)
+        }
+    }
+
+    type SimpleBF16 = simple_soft_float::Float<BF16Traits>;
+
     #[test]
     fn bf16_add() {
         let a = 0x1234;
@@ -223,10 +334,10 @@ mod tests {
         let a0 = BF16::from_bits(a);
         let b0 = BF16::from_bits(b);
         let d0 = a0.add(b0, RoundingMode::TiesToEven);
-        let a1 = simple_soft_float::F32::from_bits((a as u32) << 16);
-        let b1 = simple_soft_float::F32::from_bits((b as u32) << 16);
+        let a1 = SimpleBF16::from_bits(a);
+        let b1 = SimpleBF16::from_bits(b);
         let d1 = a1.add(&b1, Some(simple_soft_float::RoundingMode::TiesToEven), None);
-        assert_eq!(d0.to_bits(), (*d1.bits() >> 16) as u16);
+        assert_eq!(d0.to_bits(), *d1.bits());
     }
 
     #[test]
@@ -236,10 +347,10 @@ mod tests {
         let a0 = BF16::from_bits(a);
         let b0 = BF16::from_bits(b);
         let d0 = a0.sub(b0, RoundingMode::TiesToEven);
-        let a1 = simple_soft_float::F32::from_bits((a as u32) << 16);
-        let b1 = simple_soft_float::F32::from_bits((b as u32) << 16);
+        let a1 = SimpleBF16::from_bits(a);
+        let b1 = SimpleBF16::from_bits(b);
         let d1 = a1.sub(&b1, Some(simple_soft_float::RoundingMode::TiesToEven), None);
-        assert_eq!(d0.to_bits(), (*d1.bits() >> 16) as u16);
+        assert_eq!(d0.to_bits(), *d1.bits());
     }
 
     #[test]
@@ -249,10 +360,10 @@ mod tests {
         let a0 = BF16::from_bits(a);
         let b0 = BF16::from_bits(b);
         let d0 = a0.mul(b0, RoundingMode::TiesToEven);
-        let a1 = simple_soft_float::F32::from_bits((a as u32) << 16);
-        let b1 = simple_soft_float::F32::from_bits((b as u32) << 16);
+        let a1 = SimpleBF16::from_bits(a);
+        let b1 = SimpleBF16::from_bits(b);
         let d1 = a1.mul(&b1, Some(simple_soft_float::RoundingMode::TiesToEven), None);
-        assert_eq!(d0.to_bits(), (*d1.bits() >> 16) as u16);
+        assert_eq!(d0.to_bits(), *d1.bits());
     }
 
     #[test]
@@ -264,16 +375,16 @@ mod tests {
         let b0 = BF16::from_bits(b);
         let c0 = BF16::from_bits(c);
         let d0 = a0.fused_mul_add(b0, c0, RoundingMode::TiesToEven);
-        let a1 = simple_soft_float::F32::from_bits((a as u32) << 16);
-        let b1 = simple_soft_float::F32::from_bits((b as u32) << 16);
-        let c1 = simple_soft_float::F32::from_bits((c as u32) << 16);
+        let a1 = SimpleBF16::from_bits(a);
+        let b1 = SimpleBF16::from_bits(b);
+        let c1 = SimpleBF16::from_bits(c);
         let d1 = a1.fused_mul_add(
             &b1,
             &c1,
             Some(simple_soft_float::RoundingMode::TiesToEven),
             None,
         );
-        assert_eq!(d0.to_bits(), (*d1.bits() >> 16) as u16);
+        assert_eq!(d0.to_bits(), *d1.bits());
     }
 
     #[test]
@@ -283,10 +394,10 @@ mod tests {
         let a0 = BF16::from_bits(a);
         let b0 = BF16::from_bits(b);
         let d0 = a0.div(b0, RoundingMode::TiesToEven);
-        let a1 = simple_soft_float::F32::from_bits((a as u32) << 16);
-        let b1 = simple_soft_float::F32::from_bits((b as u32) << 16);
+        let a1 = SimpleBF16::from_bits(a);
+        let b1 = SimpleBF16::from_bits(b);
         let d1 = a1.div(&b1, Some(simple_soft_float::RoundingMode::TiesToEven), None);
-        assert_eq!(d0.to_bits(), (*d1.bits() >> 16) as u16);
+        assert_eq!(d0.to_bits(), *d1.bits());
     }
 
     #[test]
@@ -296,10 +407,10 @@ mod tests {
         let a0 = BF16::from_bits(a);
         let b0 = BF16::from_bits(b);
         let d0 = a0.rem(b0, RoundingMode::TiesToEven);
-        let a1 = simple_soft_float::F32::from_bits((a as u32) << 16);
-        let b1 = simple_soft_float::F32::from_bits((b as u32) << 16);
+        let a1 = SimpleBF16::from_bits(a);
+        let b1 = SimpleBF16::from_bits(b);
         let d1 = a1.ieee754_remainder(&b1, Some(simple_soft_float::RoundingMode::TiesToEven), None);
-        assert_eq!(d0.to_bits(), (*d1.bits() >> 16) as u16);
+        assert_eq!(d0.to_bits(), *d1.bits());
     }
 
     #[test]
@@ -307,9 +418,9 @@ mod tests {
         let a = 0x7654;
         let a0 = BF16::from_bits(a);
         let d0 = a0.sqrt(RoundingMode::TiesToEven);
-        let a1 = simple_soft_float::F32::from_bits((a as u32) << 16);
+        let a1 = SimpleBF16::from_bits(a);
         let d1 = a1.sqrt(Some(simple_soft_float::RoundingMode::TiesToEven), None);
-        assert_eq!(d0.to_bits(), (*d1.bits() >> 16) as u16);
+        assert_eq!(d0.to_bits(), *d1.bits());
     }
 
     #[test]
@@ -362,15 +473,97 @@ mod tests {
         assert_eq!(flag.is_invalid(), true);
     }
 
+    #[test]
+    fn bf16_rounding_mode() {
+        let modes = [
+            (
+                RoundingMode::TiesToEven,
+                simple_soft_float::RoundingMode::TiesToEven,
+            ),
+            (
+                RoundingMode::TowardZero,
+                simple_soft_float::RoundingMode::TowardZero,
+            ),
+            (
+                RoundingMode::TowardNegative,
+                simple_soft_float::RoundingMode::TowardNegative,
+            ),
+            (
+                RoundingMode::TowardPositive,
+                simple_soft_float::RoundingMode::TowardPositive,
+            ),
+            (
+                RoundingMode::TiesToAway,
+                simple_soft_float::RoundingMode::TiesToAway,
+            ),
+        ];
+        let values = [
+            0x3f81, 0xbfc1, 0x4049, 0x7f7f, 0xff7f, 0x0081, 0x0080, 0x0001, 0x807f, 0x3eab,
+        ];
+        for (rnd0, rnd1) in modes.iter() {
+            for a in values.iter() {
+                for b in values.iter() {
+                    let a0 = BF16::from_bits(*a);
+                    let b0 = BF16::from_bits(*b);
+                    let a1 = SimpleBF16::from_bits(*a);
+                    let b1 = SimpleBF16::from_bits(*b);
+
+                    let mut flag = ExceptionFlags::default();
+                    flag.set();
+                    let d0 = a0.mul(b0, *rnd0);
+                    flag.get();
+                    let mut state = simple_soft_float::FPState::default();
+                    let d1 = a1.mul(&b1, Some(*rnd1), Some(&mut state));
+                    assert_eq!(d0.to_bits(), *d1.bits());
+                    assert_eq!(
+                        flag.is_inexact(),
+                        state
+                            .status_flags
+                            .contains(simple_soft_float::StatusFlags::INEXACT)
+                    );
+                    assert_eq!(
+                        flag.is_underflow(),
+                        state
+                            .status_flags
+                            .contains(simple_soft_float::StatusFlags::UNDERFLOW)
+                    );
+                    assert_eq!(
+                        flag.is_overflow(),
+                        state
+                            .status_flags
+                            .contains(simple_soft_float::StatusFlags::OVERFLOW)
+                    );
+
+                    let d0 = a0.add(b0, *rnd0);
+                    let d1 = a1.add(&b1, Some(*rnd1), None);
+                    assert_eq!(d0.to_bits(), *d1.bits());
+
+                    let d0 = a0.div(b0, *rnd0);
+                    let d1 = a1.div(&b1, Some(*rnd1), None);
+                    assert_eq!(d0.to_bits(), *d1.bits());
+
+                    let d0 = a0.fused_mul_add(b0, a0, *rnd0);
+                    let d1 = a1.fused_mul_add(&b1, &a1, Some(*rnd1), None);
+                    assert_eq!(d0.to_bits(), *d1.bits());
+                }
+                let a0 = BF16::from_bits(*a & 0x7fff);
+                let a1 = SimpleBF16::from_bits(*a & 0x7fff);
+                let d0 = a0.sqrt(*rnd0);
+                let d1 = a1.sqrt(Some(*rnd1), None);
+                assert_eq!(d0.to_bits(), *d1.bits());
+            }
+        }
+    }
+
     #[test]
     fn from_f32() {
         let a = BF16::from_f32(0.1);
-        assert_eq!(a.to_bits(), 0x3dcc);
+        assert_eq!(a.to_bits(), 0x3dcd);
     }
 
     #[test]
     fn from_f64() {
         let a = BF16::from_f64(0.1);
-        assert_eq!(a.to_bits(), 0x3dcc);
+        assert_eq!(a.to_bits(), 0x3dcd);
     }
 }
@@ -0,0 +1,195 @@
+use std::cmp::Ordering;
+
+/// arbitrary precision unsigned integer for exact conversion between decimal and binary
+#[derive(Clone, Debug, PartialEq, Eq)]
+pub(crate) struct BigUint(Vec<u32>);
+
+impl BigUint {
+    pub(crate) fn from_u128(mut x: u128) -> Self {
+        let mut ret = Vec::new();
+        while x != 0 {
+            ret.push(x as u32);
+            x >>= 32;
+        }
+        Self(ret)
+    }
+
+    pub(crate) fn is_zero(&self) -> bool {
+        self.0.is_empty()
+    }
+
+    pub(crate) fn bit_len(&self) -> usize {
+        match self.0.last() {
+            Some(x) => self.0.len() * 32 - x.leading_zeros() as usize,
+            None => 0,
+        }
+    }
+
+    fn normalize(&mut self) {
+        while let Some(0) = self.0.last() {
+            self.0.pop();
+        }
+    }
+
+    /// self = self * x + y
+    pub(crate) fn mul_add_small(&mut self, x: u32, y: u32) {
+        let mut carry = y as u64;
+        for v in self.0.iter_mut() {
+            let t = *v as u64 * x as u64 + carry;
+            *v = t as u32;
+            carry = t >> 32;
+        }
+        if carry != 0 {
+            self.0.push(carry as u32);
+        }
+        self.normalize();
+    }
+
+    pub(crate) fn mul_pow5(&mut self, mut n: u32) {
+        // 5^13 is the largest power of 5 in u32
+        while n >= 13 {
+            self.mul_add_small(1_220_703_125, 0);
+            n -= 13;
+        }
+        self.mul_add_small(5u32.pow(n), 0);
+    }
+
+    pub(crate) fn mul_pow10(&mut self, mut n: u32) {
+        while n >= 9 {
+            self.mul_add_small(1_000_000_000, 0);
+            n -= 9;
+        }
+        self.mul_add_small(10u32.pow(n), 0);
+    }
+
+    /// self = self / x and returns the remainder
+    fn div_rem_small(&mut self, x: u32) -> u32 {
+        let mut rem = 0u64;
+        for v in self.0.iter_mut().rev() {
+            let t = (rem << 32) | *v as u64;
+            *v = (t / x as u64) as u32;
+            rem = t % x as u64;
+        }
+        self.normalize();
+        rem as u32
+    }
+
+    /// Returns decimal digits
+    pub(crate) fn to_digits(&self) -> Vec<u8> {
+        let mut x = self.clone();
+        let mut ret = Vec::new();
+        while !x.is_zero() {
+            let mut rem = x.div_rem_small(1_000_000_000);
+            for _ in 0..9 {
+                ret.push((rem % 10) as u8);
+                rem /= 10;
+            }
+        }
+        while let Some(0) = ret.last() {
+            ret.pop();
+        }
+        ret.reverse();
+        ret
+    }
+
+    pub(crate) fn add(&self, x: &Self) -> Self {
+        let mut ret = Vec::new();
+        let mut carry = 0u64;
+        for i in 0..self.0.len().max(x.0.len()) {
+            let t = self.0.get(i).copied().unwrap_or(0) as u64
+                + x.0.get(i).copied().unwrap_or(0) as u64
+                + carry;
+            ret.push(t as u32);
+            carry = t >> 32;
+        }
+        ret.push(carry as u32);
+        let mut ret = Self(ret);
+        ret.normalize();
+        ret
+    }
+
+    pub(crate) fn shl(&self, n: usize) -> Self {
+        if self.is_zero() {
+            return self.clone();
+        }
+        let (words, bits) = (n / 32, n % 32);
+        let mut ret = vec![0; words];
+        let mut carry = 0;
+        for v in &self.0 {
+            ret.push((v << bits) | carry);
+            carry = if bits == 0 { 0 } else { v >> (32 - bits) };
+        }
+        ret.push(carry);
+        let mut ret = Self(ret);
+        ret.normalize();
+        ret
+    }
+
+    /// Returns bits `[n, n + 128)` and whether any bit below `n` is set
+    pub(crate) fn shr_u128(&self, n: usize) -> (u128, bool) {
+        let (words, bits) = (n / 32, n % 32);
+        let mut ret = 0u128;
+        for i in 0..5 {
+            let v = self.0.get(words + i).copied().unwrap_or(0) as u128;
+            if i == 0 {
+                ret |= v >> bits;
+            } else {
+                ret |= v.checked_shl((32 * i - bits) as u32).unwrap_or(0);
+            }
+        }
+        let low = self.0.iter().take(words).any(|x| *x != 0);
+        let low = low
+            || self
+                .0
+                .get(words)
+                .map(|x| bits != 0 && x << (32 - bits) != 0)
+                .unwrap_or(false);
+        (ret, low)
+    }
+
+    /// self = self - x where self >= x
+    pub(crate) fn sub_assign(&mut self, x: &Self) {
+        let mut borrow = 0i64;
+        for i in 0..self.0.len() {
+            let t = self.0[i] as i64 - x.0.get(i).copied().unwrap_or(0) as i64 - borrow;
+            if t < 0 {
+                self.0[i] = (t + (1 << 32)) as u32;
+                borrow = 1;
+            } else {
+                self.0[i] = t as u32;
+                borrow = 0;
+            }
+        }
+        self.normalize();
+    }
+
+    /// Returns `self / x` which must be less than 2^128, and whether the remainder is nonzero
+    pub(crate) fn div_u128(&self, x: &Self) -> (u128, bool) {
+        let mut rem = self.clone();
+        let mut ret = 0u128;
+        let n = (self.bit_len() + 1).saturating_sub(x.bit_len()).min(128);
+        for i in (0..n).rev() {
+            let t = x.shl(i);
+            if rem >= t {
+                rem.sub_assign(&t);
+                ret |= 1 << i;
+            }
+        }
+        (ret, !rem.is_zero())
+    }
+}
+
+impl PartialOrd for BigUint {
+    fn partial_cmp(&self, x: &Self) -> Option<Ordering> {
+        Some(self.cmp(x))
+    }
+}
+
+impl Ord for BigUint {
+    fn cmp(&self, x: &Self) -> Ordering {
+        self.0
+            .len()
+            .cmp(&x.0.len())
+            .then_with(|| self.0.iter().rev().cmp(x.0.iter().rev()))
+    }
+}
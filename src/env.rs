@@ -0,0 +1,193 @@
+use crate::flush::{self, FlushMode};
+use crate::{ExceptionFlags, Specialization, Trap};
+use std::marker::PhantomData;
+use std::mem;
+
+/// floating-point environment
+///
+/// `FpEnv` saves the rounding mode, exception flags, tininess detection mode, flush modes, enabled traps and specialization,
+/// and restores them when it is dropped.
+/// It can be used like `fegetenv`/`fesetenv`/`feholdexcept`/`feupdateenv` of C.
+///
+/// ## Examples
+///
+/// ```
+/// use softfloat_wrapper::{ExceptionFlags, Float, FpEnv, RoundingMode, F16};
+///
+/// let a = F16::from_bits(0x0);
+///
+/// let env = FpEnv::hold();
+/// let _d = a.div(a, RoundingMode::TiesToEven);
+/// assert!(FpEnv::current_flags().is_invalid());
+/// drop(env);
+///
+/// let mut flag = ExceptionFlags::default();
+/// flag.get();
+/// assert!(!flag.is_invalid());
+/// ```
+pub struct FpEnv {
+    rounding_mode: u8,
+    exception_flags: u8,
+    tininess: u8,
+    flush_modes: [FlushMode; flush::FORMATS],
+    traps: ExceptionFlags,
+    specialization: Specialization,
+    // SoftFloat state is thread-local, so the saved environment must not move to another thread
+    _marker: PhantomData<*const ()>,
+}
+
+impl FpEnv {
+    /// Saves the current environment like `fegetenv`
+    pub fn get() -> Self {
+        unsafe {
+            Self {
+                rounding_mode: softfloat_sys::softfloat_roundingMode_read_helper(),
+                exception_flags: softfloat_sys::softfloat_exceptionFlags_read_helper(),
+                tininess: softfloat_sys::softfloat_detectTininess_read_helper(),
+                flush_modes: flush::load(),
+                traps: Trap::enabled(),
+                specialization: Specialization::current(),
+                _marker: PhantomData,
+            }
+        }
+    }
+
+    /// Saves the current environment, clears the exception flags and disables traps like `feholdexcept`
+    pub fn hold() -> Self {
+        let env = Self::get();
+        ExceptionFlags::default().set();
+        Trap::enable(ExceptionFlags::default());
+        env
+    }
+
+    /// Returns the exception flags of the current thread, which are raised since `hold` if it is used
+    pub fn current_flags() -> ExceptionFlags {
+        let mut flag = ExceptionFlags::default();
+        flag.get();
+        flag
+    }
+
+    /// Restores the saved environment like `fesetenv`
+    pub fn set(&self) {
+        unsafe {
+            softfloat_sys::softfloat_roundingMode_write_helper(self.rounding_mode);
+            softfloat_sys::softfloat_exceptionFlags_write_helper(self.exception_flags);
+            softfloat_sys::softfloat_detectTininess_write_helper(self.tininess);
+        }
+        flush::store(self.flush_modes);
+        Trap::enable(self.traps);
+        self.specialization.store();
+    }
+
+    /// Restores the saved environment and raises the current exception flags in it like `feupdateenv`
+    pub fn update(self) {
+        let flag = Self::current_flags();
+        self.set();
+        unsafe {
+            softfloat_sys::softfloat_exceptionFlags_write_helper(
+                self.exception_flags | flag.to_bits(),
+            );
+        }
+        mem::forget(self);
+    }
+}
+
+impl Drop for FpEnv {
+    fn drop(&mut self) {
+        self.set();
+    }
+}
+
+#[cfg(test)]
+mod tests {
+    use super::*;
+    use crate::{Float, RoundingMode, F16};
+
+    #[test]
+    fn env_hold() {
+        let a = F16::from_bits(0x1234);
+        let b = F16::from_bits(0x7654);
+        let z = F16::from_bits(0x0);
+        ExceptionFlags::from_bits(0).set();
+        let _d = a.add(b, RoundingMode::TiesToEven);
+
+        let env = FpEnv::hold();
+        assert_eq!(FpEnv::current_flags().to_bits(), 0);
+        let _d = z.div(z, RoundingMode::TiesToEven);
+        let _d = a.div(z, RoundingMode::TiesToEven);
+        assert!(FpEnv::current_flags().is_invalid());
+        assert!(FpEnv::current_flags().is_infinite());
+        assert!(!FpEnv::current_flags().is_inexact());
+        drop(env);
+
+        let mut flag = ExceptionFlags::default();
+        flag.get();
+        assert!(flag.is_inexact());
+        assert!(!flag.is_invalid());
+        assert!(!flag.is_infinite());
+    }
+
+    #[test]
+    fn env_flags() {
+        let a = F16::from_bits(0x1234);
+        let b = F16::from_bits(0x7654);
+        ExceptionFlags::from_bits(0).set();
+        let _d = a.add(b, RoundingMode::TiesToEven);
+
+        let env = FpEnv::get();
+        assert!(FpEnv::current_flags().is_inexact());
+        drop(env);
+        let _env = FpEnv::hold();
+        assert!(!FpEnv::current_flags().is_inexact());
+    }
+
+    #[test]
+    fn env_update() {
+        let a = F16::from_bits(0x1234);
+        let b = F16::from_bits(0x7654);
+        let z = F16::from_bits(0x0);
+        ExceptionFlags::from_bits(0).set();
+        let _d = a.add(b, RoundingMode::TiesToEven);
+
+        let env = FpEnv::hold();
+        let _d = z.div(z, RoundingMode::TiesToEven);
+        env.update();
+
+        let mut flag = ExceptionFlags::default();
+        flag.get();
+        assert!(flag.is_inexact());
+        assert!(flag.is_invalid());
+    }
+
+    #[test]
+    fn env_restore() {
+        unsafe {
+            softfloat_sys::softfloat_roundingMode_write_helper(softfloat_sys::softfloat_round_min);
+            softfloat_sys::softfloat_detectTininess_write_helper(
+                softfloat_sys::softfloat_tininess_beforeRounding,
+            );
+        }
+        let env = FpEnv::get();
+        unsafe {
+            softfloat_sys::softfloat_roundingMode_write_helper(softfloat_sys::softfloat_round_max);
+            softfloat_sys::softfloat_detectTininess_write_helper(
+                softfloat_sys::softfloat_tininess_afterRounding,
+            );
+        }
+        Specialization::ArmVfpv2DefaultNaN.store();
+        FlushMode::new(true, true).set::<F16>();
+        drop(env);
+        assert_eq!(Specialization::current(), Specialization::compiled());
+        assert_eq!(FlushMode::current::<F16>(), FlushMode::default());
+        unsafe {
+            assert_eq!(
+                softfloat_sys::softfloat_roundingMode_read_helper(),
+                softfloat_sys::softfloat_round_min
+            );
+            assert_eq!(
+                softfloat_sys::softfloat_detectTininess_read_helper(),
+                softfloat_sys::softfloat_tininess_beforeRounding
+            );
+        }
+    }
+}
@@ -1,4 +1,5 @@
-use crate::{Float, RoundingMode, BF16, F16, F32, F64};
+use crate::bf16::round_from_f32;
+use crate::{op, Float, Operation, RoundingMode, BF16, F16, F32, F64};
 use softfloat_sys::float128_t;
 use std::borrow::Borrow;
 
@@ -52,69 +53,95 @@ impl Float for F128 {
     }
 
     fn add<T: Borrow<Self>>(&self, x: T, rnd: RoundingMode) -> Self {
-        rnd.set();
-        let ret = unsafe { softfloat_sys::f128_add(self.0, x.borrow().0) };
-        Self(ret)
+        op::binary(Operation::Add, self, x.borrow(), |a, b| {
+            rnd.set();
+            let ret = unsafe { softfloat_sys::f128_add(a.0, b.0) };
+            Self(ret)
+        })
     }
 
     fn sub<T: Borrow<Self>>(&self, x: T, rnd: RoundingMode) -> Self {
-        rnd.set();
-        let ret = unsafe { softfloat_sys::f128_sub(self.0, x.borrow().0) };
-        Self(ret)
+        op::binary(Operation::Sub, self, x.borrow(), |a, b| {
+            rnd.set();
+            let ret = unsafe { softfloat_sys::f128_sub(a.0, b.0) };
+            Self(ret)
+        })
     }
 
     fn mul<T: Borrow<Self>>(&self, x: T, rnd: RoundingMode) -> Self {
-        rnd.set();
-        let ret = unsafe { softfloat_sys::f128_mul(self.0, x.borrow().0) };
-        Self(ret)
+        op::binary(Operation::Mul, self, x.borrow(), |a, b| {
+            rnd.set();
+            let ret = unsafe { softfloat_sys::f128_mul(a.0, b.0) };
+            Self(ret)
+        })
     }
 
     fn fused_mul_add<T: Borrow<Self>>(&self, x: T, y: T, rnd: RoundingMode) -> Self {
-        rnd.set();
-        let ret = unsafe { softfloat_sys::f128_mulAdd(self.0, x.borrow().0, y.borrow().0) };
-        Self(ret)
+        op::fused_mul_add(self, x.borrow(), y.borrow(), |a, b, c| {
+            rnd.set();
+            let ret = unsafe { softfloat_sys::f128_mulAdd(a.0, b.0, c.0) };
+            Self(ret)
+        })
     }
 
     fn div<T: Borrow<Self>>(&self, x: T, rnd: RoundingMode) -> Self {
-        rnd.set();
-        let ret = unsafe { softfloat_sys::f128_div(self.0, x.borrow().0) };
-        Self(ret)
+        op::binary(Operation::Div, self, x.borrow(), |a, b| {
+            rnd.set();
+            let ret = unsafe { softfloat_sys::f128_div(a.0, b.0) };
+            Self(ret)
+        })
     }
 
     fn rem<T: Borrow<Self>>(&self, x: T, rnd: RoundingMode) -> Self {
-        rnd.set();
-        let ret = unsafe { softfloat_sys::f128_rem(self.0, x.borrow().0) };
-        Self(ret)
+        op::binary(Operation::Rem, self, x.borrow(), |a, b| {
+            rnd.set();
+            let ret = unsafe { softfloat_sys::f128_rem(a.0, b.0) };
+            Self(ret)
+        })
     }
 
     fn sqrt(&self, rnd: RoundingMode) -> Self {
-        rnd.set();
-        let ret = unsafe { softfloat_sys::f128_sqrt(self.0) };
-        Self(ret)
+        op::unary(Operation::Sqrt, self, |a| {
+            rnd.set();
+            let ret = unsafe { softfloat_sys::f128_sqrt(a.0) };
+            Self(ret)
+        })
     }
 
     fn eq<T: Borrow<Self>>(&self, x: T) -> bool {
-        unsafe { softfloat_sys::f128_eq(self.0, x.borrow().0) }
+        op::compare(self, x.borrow(), |a, b| unsafe {
+            softfloat_sys::f128_eq(a.0, b.0)
+        })
     }
 
     fn lt<T: Borrow<Self>>(&self, x: T) -> bool {
-        unsafe { softfloat_sys::f128_lt(self.0, x.borrow().0) }
+        op::compare(self, x.borrow(), |a, b| unsafe {
+            softfloat_sys::f128_lt(a.0, b.0)
+        })
     }
 
     fn le<T: Borrow<Self>>(&self, x: T) -> bool {
-        unsafe { softfloat_sys::f128_le(self.0, x.borrow().0) }
+        op::compare(self, x.borrow(), |a, b| unsafe {
+            softfloat_sys::f128_le(a.0, b.0)
+        })
     }
 
     fn lt_quiet<T: Borrow<Self>>(&self, x: T) -> bool {
-        unsafe { softfloat_sys::f128_lt_quiet(self.0, x.borrow().0) }
+        op::compare(self, x.borrow(), |a, b| unsafe {
+            softfloat_sys::f128_lt_quiet(a.0, b.0)
+        })
     }
 
     fn le_quiet<T: Borrow<Self>>(&self, x: T) -> bool {
-        unsafe { softfloat_sys::f128_le_quiet(self.0, x.borrow().0) }
+        op::compare(self, x.borrow(), |a, b| unsafe {
+            softfloat_sys::f128_le_quiet(a.0, b.0)
+        })
     }
 
     fn eq_signaling<T: Borrow<Self>>(&self, x: T) -> bool {
-        unsafe { softfloat_sys::f128_eq_signaling(self.0, x.borrow().0) }
+        op::compare(self, x.borrow(), |a, b| unsafe {
+            softfloat_sys::f128_eq_signaling(a.0, b.0)
+        })
     }
 
     fn is_signaling_nan(&self) -> bool {
@@ -122,71 +149,94 @@ impl Float for F128 {
     }
 
     fn from_u32(x: u32, rnd: RoundingMode) -> Self {
-        rnd.set();
-        let ret = unsafe { softfloat_sys::ui32_to_f128(x) };
-        Self(ret)
+        op::from_int(x as u128, || {
+            rnd.set();
+            let ret = unsafe { softfloat_sys::ui32_to_f128(x) };
+            Self(ret)
+        })
     }
 
     fn from_u64(x: u64, rnd: RoundingMode) -> Self {
-        rnd.set();
-        let ret = unsafe { softfloat_sys::ui64_to_f128(x) };
-        Self(ret)
+        op::from_int(x as u128, || {
+            rnd.set();
+            let ret = unsafe { softfloat_sys::ui64_to_f128(x) };
+            Self(ret)
+        })
     }
 
     fn from_i32(x: i32, rnd: RoundingMode) -> Self {
-        rnd.set();
-        let ret = unsafe { softfloat_sys::i32_to_f128(x) };
-        Self(ret)
+        op::from_int(x as u128, || {
+            rnd.set();
+            let ret = unsafe { softfloat_sys::i32_to_f128(x) };
+            Self(ret)
+        })
     }
 
     fn from_i64(x: i64, rnd: RoundingMode) -> Self {
-        rnd.set();
-        let ret = unsafe { softfloat_sys::i64_to_f128(x) };
-        Self(ret)
+        op::from_int(x as u128, || {
+            rnd.set();
+            let ret = unsafe { softfloat_sys::i64_to_f128(x) };
+            Self(ret)
+        })
     }
 
     fn to_u32(&self, rnd: RoundingMode, exact: bool) -> u32 {
-        let ret = unsafe { softfloat_sys::f128_to_ui32(self.0, rnd.to_softfloat(), exact) };
-        ret as u32
+        op::to_int(self, |a| {
+            let ret = unsafe { softfloat_sys::f128_to_ui32(a.0, rnd.to_softfloat(), exact) };
+            ret as u32
+        })
     }
 
     fn to_u64(&self, rnd: RoundingMode, exact: bool) -> u64 {
-        let ret = unsafe { softfloat_sys::f128_to_ui64(self.0, rnd.to_softfloat(), exact) };
-        ret
+        op::to_int(self, |a| {
+            let ret = unsafe { softfloat_sys::f128_to_ui64(a.0, rnd.to_softfloat(), exact) };
+            ret
+        })
     }
 
     fn to_i32(&self, rnd: RoundingMode, exact: bool) -> i32 {
-        let ret = unsafe { softfloat_sys::f128_to_i32(self.0, rnd.to_softfloat(), exact) };
-        ret as i32
+        op::to_int(self, |a| {
+            let ret = unsafe { softfloat_sys::f128_to_i32(a.0, rnd.to_softfloat(), exact) };
+            ret as i32
+        })
     }
 
     fn to_i64(&self, rnd: RoundingMode, exact: bool) -> i64 {
-        let ret = unsafe { softfloat_sys::f128_to_i64(self.0, rnd.to_softfloat(), exact) };
-        ret
+        op::to_int(self, |a| {
+            let ret = unsafe { softfloat_sys::f128_to_i64(a.0, rnd.to_softfloat(), exact) };
+            ret
+        })
     }
 
     fn to_f16(&self, rnd: RoundingMode) -> F16 {
-        rnd.set();
-        let ret = unsafe { softfloat_sys::f128_to_f16(self.0) };
-        F16::from_bits(ret.v)
+        op::convert(self, |a| {
+            rnd.set();
+            let ret = unsafe { softfloat_sys::f128_to_f16(a.0) };
+            F16::from_bits(ret.v)
+        })
     }
 
     fn to_bf16(&self, rnd: RoundingMode) -> BF16 {
-        rnd.set();
-        let ret = unsafe { softfloat_sys::f128_to_f32(self.0) };
-        BF16::from_bits((ret.v >> 16) as u16)
+        op::convert(self, |a| {
+            let ret = round_from_f32(|| unsafe { softfloat_sys::f128_to_f32(a.0) }, rnd);
+            BF16::from_bits(ret.v)
+        })
     }
 
     fn to_f32(&self, rnd: RoundingMode) -> F32 {
-        rnd.set();
-        let ret = unsafe { softfloat_sys::f128_to_f32(self.0) };
-        F32::from_bits(ret.v)
+        op::convert(self, |a| {
+            rnd.set();
+            let ret = unsafe { softfloat_sys::f128_to_f32(a.0) };
+            F32::from_bits(ret.v)
+        })
     }
 
     fn to_f64(&self, rnd: RoundingMode) -> F64 {
-        rnd.set();
-        let ret = unsafe { softfloat_sys::f128_to_f64(self.0) };
-        F64::from_bits(ret.v)
+        op::convert(self, |a| {
+            rnd.set();
+            let ret = unsafe { softfloat_sys::f128_to_f64(a.0) };
+            F64::from_bits(ret.v)
+        })
     }
 
     fn to_f128(&self, _rnd: RoundingMode) -> F128 {
@@ -194,8 +244,17 @@ impl Float for F128 {
     }
 
     fn round_to_integral(&self, rnd: RoundingMode) -> Self {
-        let ret = unsafe { softfloat_sys::f128_roundToInt(self.0, rnd.to_softfloat(), false) };
-        Self(ret)
+        op::unary(Operation::RoundToIntegral, self, |a| {
+            let ret = unsafe { softfloat_sys::f128_roundToInt(a.0, rnd.to_softfloat(), false) };
+            Self(ret)
+        })
+    }
+
+    fn round_to_integral_exact(&self, rnd: RoundingMode) -> Self {
+        op::unary(Operation::RoundToIntegral, self, |a| {
+            let ret = unsafe { softfloat_sys::f128_roundToInt(a.0, rnd.to_softfloat(), true) };
+            Self(ret)
+        })
     }
 }
 
@@ -365,4 +424,17 @@ mod tests {
         let a = F128::from_f64(0.1);
         assert_eq!(a.to_bits(), 0x3ffb999999999999a000000000000000);
     }
+
+    #[test]
+    fn to_bf16() {
+        // 1 + 2^-8 + 2^-100 is above the midpoint of 1 and 1 + 2^-7
+        let a = F128::from_bits(0x3fff_0100_0000_0000_0000_0000_1000_0000);
+        let mut flag = ExceptionFlags::default();
+        flag.set();
+        assert_eq!(a.to_bf16(RoundingMode::TiesToEven).to_bits(), 0x3f81);
+        flag.get();
+        assert!(flag.is_inexact());
+        assert_eq!(a.to_bf16(RoundingMode::TowardZero).to_bits(), 0x3f80);
+        assert_eq!(a.to_bf16(RoundingMode::TowardNegative).to_bits(), 0x3f80);
+    }
 }
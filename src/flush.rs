@@ -0,0 +1,300 @@
+use crate::{ExceptionFlags, Float, Specialization};
+use num_traits::{One, Zero};
+use std::cell::Cell;
+
+/// flush-to-zero and denormals-are-zero modes
+///
+/// The modes are set per format on the current thread, like x86 MXCSR.FTZ/DAZ or ARM FPCR.FZ/FZ16.
+/// Formats are identified by their layout, and other formats than the provided ones share a mode.
+/// Flush-to-zero replaces subnormal results of operations by zero,
+/// and denormals-are-zero replaces subnormal operands by zero.
+/// Arithmetic, conversions, comparisons, rounding to integral, minimum and maximum, `scale_b` and `compose` are affected.
+/// Operations which work on the encoding, like `neg`, `abs`, `copy_sign`, `next_up`, `next_after`, `ulp`, `log_b`,
+/// `frexp`, `decompose`, `total_order` and classification, are not affected.
+///
+/// The raised flags follow the current specialization:
+///
+/// * ARM: flushed results raise underflow without inexact, and flushed operands raise input denormal
+/// * others: flushed results raise underflow and inexact, and flushed operands raise nothing
+///
+/// ## Examples
+///
+/// ```
+/// use softfloat_wrapper::{Float, FlushMode, RoundingMode, F32};
+///
+/// let a = F32::from_bits(0x00800000);
+/// let b = F32::from_bits(0x3f000000);
+/// let (d, flag) = FlushMode::new(true, false).scope::<F32, _, _>(|| {
+///     a.mul_with_flags(b, RoundingMode::TiesToEven)
+/// });
+/// assert_eq!(d.to_bits(), 0x00000000);
+/// assert!(flag.is_underflow() && flag.is_inexact());
+/// ```
+#[derive(Copy, Clone, Debug, Default, PartialEq, Eq, Hash)]
+pub struct FlushMode {
+    flush_to_zero: bool,
+    denormals_are_zero: bool,
+}
+
+// F16, BF16, F32, F64, F128 and the other formats
+pub(crate) const FORMATS: usize = 6;
+
+thread_local! {
+    static FLUSH_MODE: Cell<[FlushMode; FORMATS]> = Cell::new([FlushMode::default(); FORMATS]);
+}
+
+// index of `F` in `FLUSH_MODE`, which is identified by the layout of the format
+fn format<F: Float + ?Sized>() -> usize {
+    match (F::SIGN_POS, F::EXPONENT_POS) {
+        (15, 10) => 0,
+        (15, 7) => 1,
+        (31, 23) => 2,
+        (63, 52) => 3,
+        (127, 112) => 4,
+        _ => 5,
+    }
+}
+
+fn set_format(format: usize, mode: FlushMode) {
+    FLUSH_MODE.with(|x| {
+        let mut modes = x.get();
+        modes[format] = mode;
+        x.set(modes);
+    });
+}
+
+impl FlushMode {
+    pub fn new(flush_to_zero: bool, denormals_are_zero: bool) -> Self {
+        Self {
+            flush_to_zero,
+            denormals_are_zero,
+        }
+    }
+
+    /// Returns whether subnormal results are flushed to zero
+    pub fn flush_to_zero(&self) -> bool {
+        self.flush_to_zero
+    }
+
+    /// Returns whether subnormal operands are treated as zero
+    pub fn denormals_are_zero(&self) -> bool {
+        self.denormals_are_zero
+    }
+
+    /// Returns the mode of format `F` on the current thread
+    pub fn current<F: Float + ?Sized>() -> Self {
+        FLUSH_MODE.with(|x| x.get()[format::<F>()])
+    }
+
+    /// Sets the mode of format `F` on the current thread
+    pub fn set<F: Float + ?Sized>(&self) {
+        set_format(format::<F>(), *self);
+    }
+
+    /// Runs `f` with the mode of format `F` and restores the previous one afterwards
+    pub fn scope<F: Float + ?Sized, T, G: FnOnce() -> T>(&self, f: G) -> T {
+        let saved = Saved {
+            mode: Self::current::<F>(),
+            format: format::<F>(),
+        };
+        self.set::<F>();
+        let ret = f();
+        drop(saved);
+        ret
+    }
+}
+
+// mode of a format restored on drop, even if the closure of `scope` panics
+struct Saved {
+    mode: FlushMode,
+    format: usize,
+}
+
+impl Drop for Saved {
+    fn drop(&mut self) {
+        set_format(self.format, self.mode);
+    }
+}
+
+pub(crate) fn load() -> [FlushMode; FORMATS] {
+    FLUSH_MODE.with(|x| x.get())
+}
+
+pub(crate) fn store(modes: [FlushMode; FORMATS]) {
+    FLUSH_MODE.with(|x| x.set(modes));
+}
+
+fn is_arm() -> bool {
+    matches!(
+        Specialization::current(),
+        Specialization::ArmVfpv2 | Specialization::ArmVfpv2DefaultNaN
+    )
+}
+
+fn zero<F: Float>(sign: F::Payload) -> F {
+    let mut ret = F::from_bits(F::Payload::zero());
+    ret.set_sign(sign);
+    ret
+}
+
+/// Returns whether operand `x` is treated as zero, and raises input denormal if it is
+pub(crate) fn is_flushed<F: Float + ?Sized>(x: &F) -> bool {
+    if x.is_subnormal() && FlushMode::current::<F>().denormals_are_zero {
+        if is_arm() {
+            unsafe { softfloat_sys::softfloat_raiseFlags(ExceptionFlags::DENORMAL.to_bits()) };
+        }
+        true
+    } else {
+        false
+    }
+}
+
+/// Returns operand `x` with denormals-are-zero applied
+pub(crate) fn input<F: Float>(x: &F) -> F {
+    if is_flushed(x) {
+        zero(x.sign())
+    } else {
+        F::from_bits(x.to_bits())
+    }
+}
+
+/// Runs `op` and applies flush-to-zero to the result
+pub(crate) fn output<F: Float, O: FnOnce() -> F>(op: O) -> F {
+    if !FlushMode::current::<F>().flush_to_zero {
+        return op();
+    }
+    let flags = unsafe { softfloat_sys::softfloat_exceptionFlags_read_helper() };
+    unsafe { softfloat_sys::softfloat_exceptionFlags_write_helper(0) };
+    let ret = op();
+    let mut op_flags = unsafe { softfloat_sys::softfloat_exceptionFlags_read_helper() };
+
+    // results rounded up to the minimum normal number are flushed if tininess is detected
+    let min_normal = F::Payload::one() << F::EXPONENT_POS;
+    let tiny = ret.is_subnormal()
+        || (ret.abs().to_bits() == min_normal
+            && op_flags & softfloat_sys::softfloat_flag_underflow != 0);
+    let ret = if tiny {
+        op_flags |= softfloat_sys::softfloat_flag_underflow;
+        if is_arm() {
+            op_flags &= !softfloat_sys::softfloat_flag_inexact;
+        } else {
+            op_flags |= softfloat_sys::softfloat_flag_inexact;
+        }
+        zero(ret.sign())
+    } else {
+        ret
+    };
+    unsafe { softfloat_sys::softfloat_exceptionFlags_write_helper(flags | op_flags) };
+    ret
+}
+
+#[cfg(test)]
+mod tests {
+    use super::FlushMode;
+    use crate::{ExceptionFlags, Float, RoundingMode, Specialization, F16, F32, F64};
+
+    const FTZ: FlushMode = FlushMode {
+        flush_to_zero: true,
+        denormals_are_zero: false,
+    };
+    const DAZ: FlushMode = FlushMode {
+        flush_to_zero: false,
+        denormals_are_zero: true,
+    };
+
+    #[test]
+    fn flush_to_zero() {
+        let rnd = RoundingMode::TiesToEven;
+        let a = F32::from_bits(0x00800000);
+        let b = F32::from_bits(0x3f000000);
+        let (d, flag) = a.mul_with_flags(b, rnd);
+        assert_eq!(d.to_bits(), 0x00400000);
+        assert_eq!(flag.to_bits(), 0);
+
+        let (d, flag) = FTZ.scope::<F32, _, _>(|| a.neg().mul_with_flags(b, rnd));
+        assert_eq!(d.to_bits(), 0x80000000);
+        assert!(flag.is_underflow() && flag.is_inexact());
+        let (d, flag) = Specialization::ArmVfpv2
+            .scope(|| FTZ.scope::<F32, _, _>(|| a.mul_with_flags(F32::from_bits(0x3f000001), rnd)));
+        assert_eq!(d.to_bits(), 0x00000000);
+        assert!(flag.is_underflow() && !flag.is_inexact());
+
+        // (1 - 2^-46) * 2^-126 rounds up to the minimum normal number
+        let a = F32::from_bits(0x00fffffe);
+        let b = F32::from_bits(0x3f000001);
+        let d = Specialization::X86Sse.scope(|| FTZ.scope::<F32, _, _>(|| a.mul(b, rnd)));
+        assert_eq!(d.to_bits(), 0x00800000);
+        let d = Specialization::ArmVfpv2.scope(|| FTZ.scope::<F32, _, _>(|| a.mul(b, rnd)));
+        assert_eq!(d.to_bits(), 0x00000000);
+
+        // other formats are not affected
+        let d = FTZ.scope::<F64, _, _>(|| a.mul(b, RoundingMode::TowardZero));
+        assert_eq!(d.to_bits(), 0x007fffff);
+        let d = FTZ.scope::<F16, _, _>(|| F32::from_bits(0x38000000).to_f16(rnd));
+        assert_eq!(d.to_bits(), 0x0000);
+        assert_eq!(FlushMode::current::<F16>(), FlushMode::default());
+    }
+
+    #[test]
+    fn denormals_are_zero() {
+        let rnd = RoundingMode::TiesToEven;
+        let a = F16::from_bits(0x8001);
+        let one = F16::from_bits(0x3c00);
+        let (d, flag) = DAZ.scope::<F16, _, _>(|| a.mul_with_flags(one, rnd));
+        assert_eq!(d.to_bits(), 0x8000);
+        assert_eq!(flag.to_bits(), 0);
+        let (d, flag) = Specialization::ArmVfpv2
+            .scope(|| DAZ.scope::<F16, _, _>(|| a.add_with_flags(one, rnd)));
+        assert_eq!(d.to_bits(), 0x3c00);
+        assert!(flag.is_denormal() && !flag.is_inexact());
+
+        let (d, flag) = DAZ.scope::<F16, _, _>(|| {
+            ExceptionFlags::capture(|| {
+                (
+                    a.eq(F16::from_bits(0x0000)),
+                    a.to_i32(rnd, true),
+                    a.to_i128(RoundingMode::TowardNegative, true),
+                    a.to_f32(rnd).to_bits(),
+                )
+            })
+        });
+        assert_eq!(d, (true, 0, 0, 0x80000000));
+        assert_eq!(flag.to_bits(), 0);
+        assert_eq!(a.to_i128(RoundingMode::TowardNegative, true), -1);
+
+        // results are not flushed
+        let d = DAZ.scope::<F16, _, _>(|| F16::from_bits(0x0400).mul(F16::from_bits(0x3800), rnd));
+        assert_eq!(d.to_bits(), 0x0200);
+    }
+
+    #[test]
+    fn flush_other_operations() {
+        let rnd = RoundingMode::TiesToEven;
+        let a = F32::from_bits(0x00000001);
+        let b = F32::from_bits(0x80000000);
+        let d =
+            DAZ.scope::<F32, _, _>(|| (a.maximum(b), a.minimum_magnitude(b), a.scale_b(30, rnd)));
+        assert_eq!(
+            (d.0.to_bits(), d.1.to_bits(), d.2.to_bits()),
+            (0x00000000, 0x80000000, 0x00000000)
+        );
+
+        let c = F32::from_bits(0x00800000);
+        let (d, flag) = FTZ.scope::<F32, _, _>(|| ExceptionFlags::capture(|| c.scale_b(-1, rnd)));
+        assert_eq!(d.to_bits(), 0x00000000);
+        assert!(flag.is_underflow() && flag.is_inexact());
+        let d = FTZ.scope::<F32, _, _>(|| F32::compose(true, -130, 1, rnd));
+        assert_eq!(d.to_bits(), 0x80000000);
+
+        // operations on the encoding are not affected
+        let d = FTZ.scope::<F32, _, _>(|| F32::from_bits(0x00000000).next_up());
+        assert_eq!(d.to_bits(), 0x00000001);
+    }
+
+    #[test]
+    fn flush_scope_panic() {
+        let ret = std::panic::catch_unwind(|| FTZ.scope::<F32, _, _>(|| panic!("in scope")));
+        assert!(ret.is_err());
+        assert_eq!(FlushMode::current::<F32>(), FlushMode::default());
+    }
+}
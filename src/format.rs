@@ -0,0 +1,426 @@
+use crate::bigint::BigUint;
+use crate::{Float, BF16, F128, F16, F32, F64};
+use num_traits::{One, ToPrimitive, Zero};
+use std::fmt::{Display, Formatter, LowerHex, Result, UpperHex};
+
+// `Display` prints the shortest decimal which round-trips by Steele & White's free-format algorithm.
+// Alternate flag (`{:#}`) prints the exact decimal expansion,
+// and precision (`{:.3}`) prints the exact value rounded to nearest even.
+// `LowerHex` and `UpperHex` print C99 hexadecimal floating-point like `%a`,
+// and NaN is printed with its payload like `nan(0x1234)`, which can be parsed again.
+
+/// Returns `(f, e)` of finite nonzero value, where the absolute value is `f * 2^e`
+fn to_parts<F: Float>(x: &F) -> (u128, i32) {
+    let (_, e, f) = x.decompose().unwrap();
+    (f, e)
+}
+
+/// Returns the shortest digits and the decimal exponent `k`, where the value is `0.digits * 10^k`
+fn shortest<F: Float>(x: &F) -> (Vec<u8>, i32) {
+    let (f, e) = to_parts(x);
+    // the lower neighbor is closer at the power of 2
+    let boundary = x.fraction().is_zero() && x.exponent() > F::Payload::one();
+    // the boundaries round to even significand
+    let even = f & 1 == 0;
+
+    let one = BigUint::from_u128(1);
+    let (mut r, mut s, mut mp, mut mm) = match (e >= 0, boundary) {
+        (true, false) => (
+            BigUint::from_u128(f).shl(e as usize + 1),
+            BigUint::from_u128(2),
+            one.shl(e as usize),
+            one.shl(e as usize),
+        ),
+        (true, true) => (
+            BigUint::from_u128(f).shl(e as usize + 2),
+            BigUint::from_u128(4),
+            one.shl(e as usize + 1),
+            one.shl(e as usize),
+        ),
+        (false, false) => (
+            BigUint::from_u128(f).shl(1),
+            one.shl((1 - e) as usize),
+            one.clone(),
+            one,
+        ),
+        (false, true) => (
+            BigUint::from_u128(f).shl(2),
+            one.shl((2 - e) as usize),
+            BigUint::from_u128(2),
+            one,
+        ),
+    };
+
+    let high = |r: &BigUint, mp: &BigUint, s: &BigUint| {
+        let t = r.add(mp);
+        if even {
+            t >= *s
+        } else {
+            t > *s
+        }
+    };
+
+    // estimate k, and fix it up
+    let bits = 128 - f.leading_zeros() as i32 + e;
+    let mut k = (bits as f64 * std::f64::consts::LOG10_2).ceil() as i32;
+    if k >= 0 {
+        s.mul_pow10(k as u32);
+    } else {
+        r.mul_pow10(-k as u32);
+        mp.mul_pow10(-k as u32);
+        mm.mul_pow10(-k as u32);
+    }
+    while high(&r, &mp, &s) {
+        s.mul_add_small(10, 0);
+        k += 1;
+    }
+    loop {
+        let mut t = r.add(&mp);
+        t.mul_add_small(10, 0);
+        if (even && t >= s) || (!even && t > s) {
+            break;
+        }
+        r.mul_add_small(10, 0);
+        mp.mul_add_small(10, 0);
+        mm.mul_add_small(10, 0);
+        k -= 1;
+    }
+
+    let mut digits = Vec::new();
+    loop {
+        r.mul_add_small(10, 0);
+        mp.mul_add_small(10, 0);
+        mm.mul_add_small(10, 0);
+        let mut d = 0;
+        while r >= s {
+            r.sub_assign(&s);
+            d += 1;
+        }
+        let low = if even { r <= mm } else { r < mm };
+        match (low, high(&r, &mp, &s)) {
+            (false, false) => digits.push(d),
+            (true, false) => {
+                digits.push(d);
+                break;
+            }
+            (false, true) => {
+                digits.push(d + 1);
+                break;
+            }
+            (true, true) => {
+                digits.push(if r.shl(1) < s { d } else { d + 1 });
+                break;
+            }
+        }
+    }
+    (digits, k)
+}
+
+/// Returns the exact integral and fractional digits
+fn exact<F: Float>(x: &F) -> (Vec<u8>, Vec<u8>) {
+    let (f, e) = to_parts(x);
+    let (f, e) = (f >> f.trailing_zeros(), e + f.trailing_zeros() as i32);
+    if e >= 0 {
+        (
+            BigUint::from_u128(f).shl(e as usize).to_digits(),
+            Vec::new(),
+        )
+    } else {
+        // f / 2^-e = f * 5^-e / 10^-e
+        let mut x = BigUint::from_u128(f);
+        x.mul_pow5(-e as u32);
+        let mut digits = x.to_digits();
+        let len = (-e) as usize;
+        if digits.len() <= len {
+            let mut zeros = vec![0; len + 1 - digits.len()];
+            zeros.append(&mut digits);
+            digits = zeros;
+        }
+        let frac = digits.split_off(digits.len() - len);
+        (digits, frac)
+    }
+}
+
+/// Rounds the exact digits to `precision` fractional digits
+fn fixed(mut int: Vec<u8>, mut frac: Vec<u8>, precision: usize) -> (Vec<u8>, Vec<u8>) {
+    if frac.len() <= precision {
+        frac.resize(precision, 0);
+        return (int, frac);
+    }
+    let rest = frac.split_off(precision);
+    let last = frac.last().or_else(|| int.last()).copied().unwrap_or(0);
+    let up = rest[0] > 5 || (rest[0] == 5 && (rest[1..].iter().any(|x| *x != 0) || last & 1 == 1));
+    if up {
+        let mut carry = true;
+        for d in frac.iter_mut().rev().chain(int.iter_mut().rev()) {
+            if *d == 9 {
+                *d = 0;
+            } else {
+                *d += 1;
+                carry = false;
+                break;
+            }
+        }
+        if carry {
+            int.insert(0, 1);
+        }
+    }
+    (int, frac)
+}
+
+fn to_string(int: &[u8], frac: &[u8]) -> String {
+    let mut ret: String = if int.is_empty() {
+        "0".to_string()
+    } else {
+        int.iter().map(|x| (b'0' + x) as char).collect()
+    };
+    if !frac.is_empty() {
+        ret.push('.');
+        ret.extend(frac.iter().map(|x| (b'0' + x) as char));
+    }
+    ret
+}
+
+fn format<F: Float>(x: &F, f: &mut Formatter) -> Result {
+    if x.is_nan() {
+        return f.pad("NaN");
+    }
+    let ret = if x.is_positive_infinity() || x.is_negative_infinity() {
+        "inf".to_string()
+    } else if x.is_zero() {
+        to_string(&[], &vec![0; f.precision().unwrap_or(0)])
+    } else if f.alternate() {
+        let (int, frac) = exact(x);
+        to_string(&int, &frac)
+    } else if let Some(precision) = f.precision() {
+        let (int, frac) = exact(x);
+        let (int, frac) = fixed(int, frac, precision);
+        to_string(&int, &frac)
+    } else {
+        let (digits, k) = shortest(x);
+        if k <= 0 {
+            let mut frac = vec![0; -k as usize];
+            frac.extend(digits);
+            to_string(&[], &frac)
+        } else if k as usize >= digits.len() {
+            let mut int = digits;
+            int.resize(k as usize, 0);
+            to_string(&int, &[])
+        } else {
+            let (int, frac) = digits.split_at(k as usize);
+            to_string(int, frac)
+        }
+    };
+    f.pad_integral(!x.is_negative(), "", &ret)
+}
+
+fn format_hex<F: Float>(x: &F, f: &mut Formatter, upper: bool) -> Result {
+    let fraction = x.fraction().to_u128().unwrap();
+    let ret = if x.is_nan() {
+        let quiet_bit = 1 << (F::EXPONENT_POS - 1);
+        let name = if x.is_signaling_nan() { "snan" } else { "nan" };
+        let payload = fraction & !quiet_bit;
+        if payload == 0 {
+            name.to_string()
+        } else {
+            format!("{}(0x{:x})", name, payload)
+        }
+    } else if x.is_positive_infinity() || x.is_negative_infinity() {
+        "inf".to_string()
+    } else if x.is_zero() {
+        "0x0p+0".to_string()
+    } else {
+        let bias = (F::EXPONENT_BIT.to_i32().unwrap()) >> 1;
+        let exponent = x.exponent().to_i32().unwrap();
+        let (int, exponent) = if exponent == 0 {
+            (0, 1 - bias)
+        } else {
+            (1, exponent - bias)
+        };
+        // align the fraction to hexadecimal digits, without `div_ceil` which requires Rust 1.73
+        #[allow(clippy::manual_div_ceil)]
+        let width = (F::EXPONENT_POS + 3) / 4;
+        let fraction = fraction << (width * 4 - F::EXPONENT_POS);
+        let digits = format!("{:01$x}", fraction, width);
+        let digits = digits.trim_end_matches('0');
+        if digits.is_empty() {
+            format!("0x{}p{:+}", int, exponent)
+        } else {
+            format!("0x{}.{}p{:+}", int, digits, exponent)
+        }
+    };
+    let ret = if upper { ret.to_uppercase() } else { ret };
+    if x.is_nan() {
+        f.pad(&format!(
+            "{}{}",
+            if x.is_negative() { "-" } else { "" },
+            ret
+        ))
+    } else {
+        f.pad_integral(!x.is_negative(), "", &ret)
+    }
+}
+
+macro_rules! impl_display {
+    ($t:ty) => {
+        impl Display for $t {
+            fn fmt(&self, f: &mut Formatter) -> Result {
+                format(self, f)
+            }
+        }
+
+        impl LowerHex for $t {
+            fn fmt(&self, f: &mut Formatter) -> Result {
+                format_hex(self, f, false)
+            }
+        }
+
+        impl UpperHex for $t {
+            fn fmt(&self, f: &mut Formatter) -> Result {
+                format_hex(self, f, true)
+            }
+        }
+    };
+}
+
+impl_display!(F16);
+impl_display!(BF16);
+impl_display!(F32);
+impl_display!(F64);
+impl_display!(F128);
+
+#[cfg(test)]
+mod tests {
+    use crate::{Float, RoundingMode, BF16, F128, F16, F32, F64};
+
+    #[test]
+    fn display_shortest() {
+        let values = [
+            0.0,
+            -0.0,
+            1.0,
+            -1.5,
+            0.1,
+            0.3,
+            1e21,
+            1e-7,
+            123.456,
+            1e300,
+            5e-324,
+            2.2250738585072014e-308,
+            1.7976931348623157e308,
+            9007199254740993.0,
+            0.30000000000000004,
+            f64::INFINITY,
+            f64::NEG_INFINITY,
+            1.0 / 3.0,
+            2.0f64.powi(-1022) * 3.0,
+        ];
+        for x in values.iter() {
+            let d = F64::from_bits(x.to_bits());
+            assert_eq!(format!("{}", d), format!("{}", x));
+            let d = F32::from_bits((*x as f32).to_bits());
+            assert_eq!(format!("{}", d), format!("{}", *x as f32));
+        }
+        let d = F64::from_bits(f64::NAN.to_bits());
+        assert_eq!(format!("{}", d), "NaN");
+    }
+
+    #[test]
+    fn display_round_trip() {
+        let values = [
+            0x3c00, 0x3555, 0x7bff, 0x0001, 0x03ff, 0x0400, 0xc248, 0x2e66,
+        ];
+        for x in values.iter() {
+            let d = F16::from_bits(*x);
+            let s = format!("{}", d);
+            assert_eq!(s.parse::<F16>().unwrap().to_bits(), *x, "{}", s);
+            let d = BF16::from_bits(*x);
+            let s = format!("{}", d);
+            assert_eq!(s.parse::<BF16>().unwrap().to_bits(), *x, "{}", s);
+        }
+        assert_eq!(format!("{}", F16::from_bits(0x3555)), "0.3333");
+        assert_eq!(format!("{}", F16::from_bits(0x0001)), "0.00000006");
+        assert_eq!(format!("{}", BF16::from_bits(0x3f81)), "1.01");
+
+        let d = F128::from_bits(0x3ffb999999999999999999999999999a);
+        assert_eq!(format!("{}", d), "0.1");
+        let d = F128::from_u64(3, RoundingMode::TiesToEven);
+        let d = F128::from_u64(1, RoundingMode::TiesToEven).div(d, RoundingMode::TiesToEven);
+        assert_eq!(format!("{}", d), "0.3333333333333333333333333333333333");
+        let d = F128::from_bits(0x0000_0000_0000_0000_0000_0000_0000_0001);
+        let s = format!("{}", d);
+        assert_eq!(&s[s.len() - 6..], "000006");
+        assert_eq!(s.parse::<F128>().unwrap().to_bits(), 1);
+    }
+
+    #[test]
+    fn display_exact() {
+        let d = F32::from_bits(0x3dcccccd);
+        assert_eq!(format!("{:#}", d), "0.100000001490116119384765625");
+        assert_eq!(format!("{:#}", F16::from_bits(0x7bff)), "65504");
+        assert_eq!(format!("{:#}", F16::from_bits(0xbc00)), "-1");
+        let d = format!("{:#}", F16::from_bits(0x0001));
+        assert_eq!(d, "0.000000059604644775390625");
+        let d = format!("{:#}", F64::from_bits(1));
+        assert_eq!(d.len(), 1076);
+        assert!(d.ends_with("5625"));
+    }
+
+    #[test]
+    fn display_hex() {
+        let d = F32::from_bits(0xc048f5c3);
+        assert_eq!(format!("{:x}", d), "-0x1.91eb86p+1");
+        assert_eq!(format!("{:X}", d), "-0X1.91EB86P+1");
+        assert_eq!(
+            format!("{:x}", F64::from_bits(12.0f64.to_bits())),
+            "0x1.8p+3"
+        );
+        assert_eq!(
+            format!("{:x}", F64::from_bits(1)),
+            "0x0.0000000000001p-1022"
+        );
+        assert_eq!(format!("{:x}", F16::from_bits(0x3c01)), "0x1.004p+0");
+        assert_eq!(format!("{:x}", F16::from_bits(0x8000)), "-0x0p+0");
+        assert_eq!(format!("{:x}", BF16::from_bits(0x3fff)), "0x1.fep+0");
+        assert_eq!(
+            format!("{:x}", F128::from_bits(0x3fff << 112 | 1)),
+            "0x1.0000000000000000000000000001p+0"
+        );
+        assert_eq!(format!("{:x}", F32::from_bits(0xff800000)), "-inf");
+        assert_eq!(format!("{:X}", F32::from_bits(0x7fc00000)), "NAN");
+        assert_eq!(format!("{:x}", F32::from_bits(0xffc01234)), "-nan(0x1234)");
+        assert_eq!(format!("{:x}", F32::from_bits(0x7f800001)), "snan(0x1)");
+        assert_eq!(format!("{:>8x}", F16::from_bits(0x3c00)), "  0x1p+0");
+
+        let values = [
+            0x3c00, 0x3555, 0x7bff, 0x0001, 0x03ff, 0x0400, 0xc248, 0xfe01, 0x7d23,
+        ];
+        for x in values.iter() {
+            let s = format!("{:x}", F16::from_bits(*x));
+            assert_eq!(s.parse::<F16>().unwrap().to_bits(), *x, "{}", s);
+            let s = format!("{:X}", BF16::from_bits(*x));
+            assert_eq!(s.parse::<BF16>().unwrap().to_bits(), *x, "{}", s);
+        }
+    }
+
+    #[test]
+    fn display_precision() {
+        let values: [f64; 11] = [
+            0.0, 0.1, 0.125, 0.375, 2.5, -1.5, 9.99, 1e-10, 123456.789, 0.5, 1.5,
+        ];
+        for x in values.iter() {
+            for p in 0..5 {
+                let d = F64::from_bits(x.to_bits());
+                assert_eq!(format!("{:.*}", p, d), format!("{:.*}", p, x));
+            }
+        }
+        let d = F32::from_bits(1.5f32.to_bits());
+        assert_eq!(format!("{:>8.2}", d), "    1.50");
+        assert_eq!(format!("{:+}", d), "+1.5");
+        assert_eq!(
+            format!("{:08.3}", -0.25f64),
+            format!("{:08.3}", F64::from_bits((-0.25f64).to_bits()))
+        );
+    }
+}
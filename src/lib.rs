@@ -20,28 +20,56 @@
 //!     println!("{} + {} = {}", a, b, d);
 //! }
 //! ```
+//!
+//! ## Thread safety
+//!
+//! The rounding mode, exception flags and tininess detection mode of SoftFloat are thread-local
+//! because softfloat-sys always builds SoftFloat with `THREAD_LOCAL` defined as `_Thread_local`.
+//! So operations on a thread never change the flags or the rounding mode observed by other threads.
 
 mod bf16;
+mod bigint;
+mod env;
 mod f128;
 mod f16;
 mod f32;
 mod f64;
+mod flush;
+mod format;
+mod num;
+mod op;
+mod parse;
+mod round;
+mod specialize;
+mod total_ord;
+mod trap;
+mod with_mode;
 pub use crate::bf16::BF16;
+pub use crate::env::FpEnv;
 pub use crate::f128::F128;
 pub use crate::f16::F16;
 pub use crate::f32::F32;
 pub use crate::f64::F64;
+pub use crate::flush::FlushMode;
+pub use crate::specialize::Specialization;
+pub use crate::total_ord::{TotalOrd, TotalOrdSlice};
+pub use crate::trap::{Operation, Trap};
+pub use crate::with_mode::WithMode;
 
 use num_traits::{
     identities::{One, Zero},
-    PrimInt,
+    ParseFloatError, PrimInt,
 };
 use std::borrow::Borrow;
 use std::cmp::Ordering;
-use std::fmt::{LowerHex, UpperHex};
+use std::error::Error;
+use std::fmt::{self, LowerHex, UpperHex};
+use std::num::FpCategory;
+use std::ops::{BitAnd, BitAndAssign, BitOr, BitOrAssign, Not};
+use std::str::FromStr;
 
 /// floating-point rounding mode defined by standard
-#[derive(Copy, Clone, Debug)]
+#[derive(Copy, Clone, Debug, PartialEq, Eq, Hash)]
 pub enum RoundingMode {
     /// to nearest, ties to even
     TiesToEven,
@@ -53,10 +81,27 @@ pub enum RoundingMode {
     TowardPositive,
     /// to nearest, ties away from zero
     TiesToAway,
+    /// to odd, which sets the least significant bit of inexact results
+    ToOdd,
 }
 
 impl RoundingMode {
-    fn set(&self) {
+    /// Returns the dynamic rounding mode of the current thread
+    ///
+    /// It is the mode set by `set` or used by the last operation.
+    pub fn current() -> Self {
+        match unsafe { softfloat_sys::softfloat_roundingMode_read_helper() } {
+            softfloat_sys::softfloat_round_minMag => RoundingMode::TowardZero,
+            softfloat_sys::softfloat_round_min => RoundingMode::TowardNegative,
+            softfloat_sys::softfloat_round_max => RoundingMode::TowardPositive,
+            softfloat_sys::softfloat_round_near_maxMag => RoundingMode::TiesToAway,
+            softfloat_sys::softfloat_round_odd => RoundingMode::ToOdd,
+            _ => RoundingMode::TiesToEven,
+        }
+    }
+
+    /// Sets the dynamic rounding mode of the current thread
+    pub fn set(&self) {
         unsafe {
             softfloat_sys::softfloat_roundingMode_write_helper(self.to_softfloat());
         }
@@ -69,12 +114,85 @@ impl RoundingMode {
             RoundingMode::TowardNegative => softfloat_sys::softfloat_round_min,
             RoundingMode::TowardPositive => softfloat_sys::softfloat_round_max,
             RoundingMode::TiesToAway => softfloat_sys::softfloat_round_near_maxMag,
+            RoundingMode::ToOdd => softfloat_sys::softfloat_round_odd,
+        }
+    }
+}
+
+/// tininess detection mode defined by standard
+///
+/// Underflow is signaled when a tiny result is inexact.
+/// x86 and RISC-V detect tininess after rounding, and ARM detects it before rounding.
+///
+/// ## Examples
+///
+/// ```
+/// use softfloat_wrapper::{Float, RoundingMode, Tininess, F32};
+///
+/// // (1 - 2^-46) * 2^-126 rounds up to the minimum normal number
+/// let a = F32::from_bits(0x00fffffe);
+/// let b = F32::from_bits(0x3f000001);
+/// let (_, flag) = Tininess::BeforeRounding.scope(|| a.mul_with_flags(b, RoundingMode::TiesToEven));
+/// assert!(flag.is_underflow());
+/// let (_, flag) = Tininess::AfterRounding.scope(|| a.mul_with_flags(b, RoundingMode::TiesToEven));
+/// assert!(!flag.is_underflow());
+/// ```
+#[derive(Copy, Clone, Debug, PartialEq, Eq, Hash)]
+pub enum Tininess {
+    /// tiny if the result rounded with unbounded exponent is less than the minimum normal number
+    AfterRounding,
+    /// tiny if the exact result is less than the minimum normal number
+    BeforeRounding,
+}
+
+impl Tininess {
+    /// Returns the tininess detection mode of the current thread
+    pub fn current() -> Self {
+        let x = unsafe { softfloat_sys::softfloat_detectTininess_read_helper() };
+        if x == softfloat_sys::softfloat_tininess_beforeRounding {
+            Tininess::BeforeRounding
+        } else {
+            Tininess::AfterRounding
+        }
+    }
+
+    /// Sets the tininess detection mode of the current thread
+    ///
+    /// It is also set to the default of the specialization by `Specialization::set`.
+    pub fn set(&self) {
+        unsafe { softfloat_sys::softfloat_detectTininess_write_helper(self.to_softfloat()) };
+    }
+
+    /// Runs `f` with the tininess detection mode and restores the previous one afterwards
+    pub fn scope<T, F: FnOnce() -> T>(&self, f: F) -> T {
+        let saved = SavedTininess(Self::current());
+        self.set();
+        let ret = f();
+        drop(saved);
+        ret
+    }
+
+    fn to_softfloat(self) -> u8 {
+        match self {
+            Tininess::AfterRounding => softfloat_sys::softfloat_tininess_afterRounding,
+            Tininess::BeforeRounding => softfloat_sys::softfloat_tininess_beforeRounding,
         }
     }
 }
 
+// tininess detection mode restored on drop, even if the closure of `Tininess::scope` panics
+struct SavedTininess(Tininess);
+
+impl Drop for SavedTininess {
+    fn drop(&mut self) {
+        self.0.set();
+    }
+}
+
 /// exception flags defined by standard
 ///
+/// The flags are raised in the thread-local state of SoftFloat.
+///
 /// ## Examples
 ///
 /// ```
@@ -90,15 +208,32 @@ impl RoundingMode {
 /// flag.get();
 /// assert!(flag.is_invalid());
 /// ```
-#[derive(Copy, Clone, Debug, Default)]
+#[derive(Copy, Clone, Debug, Default, PartialEq, Eq, Hash)]
 pub struct ExceptionFlags(u8);
 
 impl ExceptionFlags {
-    const FLAG_INEXACT: u8 = softfloat_sys::softfloat_flag_inexact;
-    const FLAG_INFINITE: u8 = softfloat_sys::softfloat_flag_infinite;
-    const FLAG_INVALID: u8 = softfloat_sys::softfloat_flag_invalid;
-    const FLAG_OVERFLOW: u8 = softfloat_sys::softfloat_flag_overflow;
-    const FLAG_UNDERFLOW: u8 = softfloat_sys::softfloat_flag_underflow;
+    /// invalid operation (NV)
+    pub const INVALID: Self = Self(softfloat_sys::softfloat_flag_invalid);
+    /// division by zero (DZ)
+    pub const INFINITE: Self = Self(softfloat_sys::softfloat_flag_infinite);
+    /// overflow (OF)
+    pub const OVERFLOW: Self = Self(softfloat_sys::softfloat_flag_overflow);
+    /// underflow (UF)
+    pub const UNDERFLOW: Self = Self(softfloat_sys::softfloat_flag_underflow);
+    /// inexact (NX)
+    pub const INEXACT: Self = Self(softfloat_sys::softfloat_flag_inexact);
+    /// input denormal (ID), which is not a SoftFloat flag and raised by `FlushMode`
+    pub const DENORMAL: Self = Self(0x20);
+
+    // all flags with their names in display order
+    const NAMES: [(Self, &'static str); 6] = [
+        (Self::INVALID, "NV"),
+        (Self::INFINITE, "DZ"),
+        (Self::OVERFLOW, "OF"),
+        (Self::UNDERFLOW, "UF"),
+        (Self::INEXACT, "NX"),
+        (Self::DENORMAL, "ID"),
+    ];
 
     pub fn from_bits(x: u8) -> Self {
         Self(x)
@@ -114,23 +249,67 @@ impl ExceptionFlags {
     }
 
     pub fn is_inexact(&self) -> bool {
-        self.0 & Self::FLAG_INEXACT != 0
+        self.contains(Self::INEXACT)
     }
 
     pub fn is_infinite(&self) -> bool {
-        self.0 & Self::FLAG_INFINITE != 0
+        self.contains(Self::INFINITE)
     }
 
     pub fn is_invalid(&self) -> bool {
-        self.0 & Self::FLAG_INVALID != 0
+        self.contains(Self::INVALID)
     }
 
     pub fn is_overflow(&self) -> bool {
-        self.0 & Self::FLAG_OVERFLOW != 0
+        self.contains(Self::OVERFLOW)
     }
 
     pub fn is_underflow(&self) -> bool {
-        self.0 & Self::FLAG_UNDERFLOW != 0
+        self.contains(Self::UNDERFLOW)
+    }
+
+    /// input denormal flag of ARM, see `FlushMode`
+    pub fn is_denormal(&self) -> bool {
+        self.contains(Self::DENORMAL)
+    }
+
+    /// Returns whether no flag is raised
+    pub fn is_empty(&self) -> bool {
+        self.0 == 0
+    }
+
+    /// Returns whether all flags of `x` are raised
+    pub fn contains(&self, x: Self) -> bool {
+        self.0 & x.0 == x.0
+    }
+
+    /// Raises the flags of `x`
+    pub fn insert(&mut self, x: Self) {
+        self.0 |= x.0;
+    }
+
+    /// Clears the flags of `x`
+    pub fn remove(&mut self, x: Self) {
+        self.0 &= !x.0;
+    }
+
+    /// Returns an iterator over the raised flags
+    ///
+    /// ## Examples
+    ///
+    /// ```
+    /// use softfloat_wrapper::ExceptionFlags;
+    ///
+    /// let flag = ExceptionFlags::INEXACT | ExceptionFlags::OVERFLOW;
+    /// let d: Vec<_> = flag.iter().collect();
+    /// assert_eq!(d, vec![ExceptionFlags::OVERFLOW, ExceptionFlags::INEXACT]);
+    /// ```
+    pub fn iter(&self) -> impl Iterator<Item = Self> {
+        let x = *self;
+        Self::NAMES
+            .iter()
+            .map(|(flag, _)| *flag)
+            .filter(move |flag| x.contains(*flag))
     }
 
     pub fn set(&self) {
@@ -143,8 +322,281 @@ impl ExceptionFlags {
         let x = unsafe { softfloat_sys::softfloat_exceptionFlags_read_helper() };
         self.0 = x;
     }
+
+    /// Runs `f` with cleared flags and returns its result with the flags raised by it.
+    ///
+    /// The flags and rounding mode of the caller are restored afterwards.
+    ///
+    /// ## Examples
+    ///
+    /// ```
+    /// use softfloat_wrapper::{ExceptionFlags, Float, RoundingMode, F16};
+    ///
+    /// let a = F16::from_bits(0x0);
+    /// let (_d, flag) = ExceptionFlags::capture(|| a.div(a, RoundingMode::TiesToEven));
+    /// assert!(flag.is_invalid());
+    /// ```
+    pub fn capture<T, F: FnOnce() -> T>(f: F) -> (T, Self) {
+        let mut saved = Captured {
+            flags: Self::default(),
+            rnd: unsafe { softfloat_sys::softfloat_roundingMode_read_helper() },
+        };
+        saved.flags.get();
+        Self::default().set();
+        let ret = f();
+        let mut flag = Self::default();
+        flag.get();
+        drop(saved);
+        (ret, flag)
+    }
+}
+
+// flags and rounding mode of the caller of `capture` restored on drop, even if the closure panics
+struct Captured {
+    flags: ExceptionFlags,
+    rnd: u8,
 }
 
+impl Drop for Captured {
+    fn drop(&mut self) {
+        self.flags.set();
+        unsafe { softfloat_sys::softfloat_roundingMode_write_helper(self.rnd) };
+    }
+}
+
+impl BitOr for ExceptionFlags {
+    type Output = Self;
+
+    fn bitor(self, x: Self) -> Self {
+        Self(self.0 | x.0)
+    }
+}
+
+impl BitAnd for ExceptionFlags {
+    type Output = Self;
+
+    fn bitand(self, x: Self) -> Self {
+        Self(self.0 & x.0)
+    }
+}
+
+impl BitOrAssign for ExceptionFlags {
+    fn bitor_assign(&mut self, x: Self) {
+        self.0 |= x.0;
+    }
+}
+
+impl BitAndAssign for ExceptionFlags {
+    fn bitand_assign(&mut self, x: Self) {
+        self.0 &= x.0;
+    }
+}
+
+impl Not for ExceptionFlags {
+    type Output = Self;
+
+    fn not(self) -> Self {
+        let all = Self::NAMES.iter().fold(0, |acc, (flag, _)| acc | flag.0);
+        Self(!self.0 & all)
+    }
+}
+
+/// Formats the raised flags like `NV DZ OF UF NX`
+///
+/// ```
+/// use softfloat_wrapper::ExceptionFlags;
+///
+/// let flag = ExceptionFlags::INVALID | ExceptionFlags::INEXACT;
+/// assert_eq!(flag.to_string(), "NV NX");
+/// assert_eq!("NX NV".parse::<ExceptionFlags>().unwrap(), flag);
+/// ```
+impl fmt::Display for ExceptionFlags {
+    fn fmt(&self, f: &mut fmt::Formatter) -> fmt::Result {
+        let names: Vec<_> = Self::NAMES
+            .iter()
+            .filter(|(flag, _)| self.contains(*flag))
+            .map(|(_, name)| *name)
+            .collect();
+        f.pad(&names.join(" "))
+    }
+}
+
+/// error of parsing `ExceptionFlags`
+#[derive(Clone, Debug, PartialEq, Eq)]
+pub struct ParseExceptionFlagsError(String);
+
+impl fmt::Display for ParseExceptionFlagsError {
+    fn fmt(&self, f: &mut fmt::Formatter) -> fmt::Result {
+        write!(f, "invalid exception flag: {}", self.0)
+    }
+}
+
+impl Error for ParseExceptionFlagsError {}
+
+impl FromStr for ExceptionFlags {
+    type Err = ParseExceptionFlagsError;
+
+    /// Parses whitespace-separated flag names like `NV NX`
+    fn from_str(s: &str) -> Result<Self, Self::Err> {
+        let mut ret = Self::default();
+        for x in s.split_whitespace() {
+            let flag = Self::NAMES
+                .iter()
+                .find(|(_, name)| name.eq_ignore_ascii_case(x))
+                .ok_or_else(|| ParseExceptionFlagsError(x.to_string()))?;
+            ret.insert(flag.0);
+        }
+        Ok(ret)
+    }
+}
+
+/// floating-point class defined by standard
+#[derive(Copy, Clone, Debug, PartialEq, Eq, Hash)]
+pub enum FloatClass {
+    SignalingNaN,
+    QuietNaN,
+    NegativeInfinity,
+    NegativeNormal,
+    NegativeSubnormal,
+    NegativeZero,
+    PositiveZero,
+    PositiveSubnormal,
+    PositiveNormal,
+    PositiveInfinity,
+}
+
+impl FloatClass {
+    /// Returns the 10-bit result of RISC-V `fclass` instruction
+    pub fn fclass_mask(&self) -> u16 {
+        match self {
+            FloatClass::NegativeInfinity => 1 << 0,
+            FloatClass::NegativeNormal => 1 << 1,
+            FloatClass::NegativeSubnormal => 1 << 2,
+            FloatClass::NegativeZero => 1 << 3,
+            FloatClass::PositiveZero => 1 << 4,
+            FloatClass::PositiveSubnormal => 1 << 5,
+            FloatClass::PositiveNormal => 1 << 6,
+            FloatClass::PositiveInfinity => 1 << 7,
+            FloatClass::SignalingNaN => 1 << 8,
+            FloatClass::QuietNaN => 1 << 9,
+        }
+    }
+}
+
+impl From<FloatClass> for FpCategory {
+    fn from(x: FloatClass) -> Self {
+        match x {
+            FloatClass::SignalingNaN | FloatClass::QuietNaN => FpCategory::Nan,
+            FloatClass::NegativeInfinity | FloatClass::PositiveInfinity => FpCategory::Infinite,
+            FloatClass::NegativeNormal | FloatClass::PositiveNormal => FpCategory::Normal,
+            FloatClass::NegativeSubnormal | FloatClass::PositiveSubnormal => FpCategory::Subnormal,
+            FloatClass::NegativeZero | FloatClass::PositiveZero => FpCategory::Zero,
+        }
+    }
+}
+
+/// comparison predicate defined by standard
+///
+/// Signaling predicates raise invalid exception for any NaN,
+/// and quiet predicates raise it only for signaling NaN.
+#[derive(Copy, Clone, Debug, PartialEq, Eq, Hash)]
+pub enum Predicate {
+    QuietEqual,
+    QuietNotEqual,
+    QuietGreater,
+    QuietGreaterEqual,
+    QuietLess,
+    QuietLessEqual,
+    QuietUnordered,
+    QuietNotGreater,
+    QuietLessUnordered,
+    QuietNotLess,
+    QuietGreaterUnordered,
+    QuietOrdered,
+    SignalingEqual,
+    SignalingNotEqual,
+    SignalingGreater,
+    SignalingGreaterEqual,
+    SignalingLess,
+    SignalingLessEqual,
+    SignalingNotGreater,
+    SignalingLessUnordered,
+    SignalingNotLess,
+    SignalingGreaterUnordered,
+}
+
+impl Predicate {
+    fn is_signaling(&self) -> bool {
+        matches!(
+            self,
+            Predicate::SignalingEqual
+                | Predicate::SignalingNotEqual
+                | Predicate::SignalingGreater
+                | Predicate::SignalingGreaterEqual
+                | Predicate::SignalingLess
+                | Predicate::SignalingLessEqual
+                | Predicate::SignalingNotGreater
+                | Predicate::SignalingLessUnordered
+                | Predicate::SignalingNotLess
+                | Predicate::SignalingGreaterUnordered
+        )
+    }
+
+    fn test(&self, x: Option<Ordering>) -> bool {
+        use Ordering::{Equal, Greater, Less};
+        match self {
+            Predicate::QuietEqual | Predicate::SignalingEqual => x == Some(Equal),
+            Predicate::QuietNotEqual | Predicate::SignalingNotEqual => x != Some(Equal),
+            Predicate::QuietGreater | Predicate::SignalingGreater => x == Some(Greater),
+            Predicate::QuietGreaterEqual | Predicate::SignalingGreaterEqual => {
+                x == Some(Greater) || x == Some(Equal)
+            }
+            Predicate::QuietLess | Predicate::SignalingLess => x == Some(Less),
+            Predicate::QuietLessEqual | Predicate::SignalingLessEqual => {
+                x == Some(Less) || x == Some(Equal)
+            }
+            Predicate::QuietUnordered => x.is_none(),
+            Predicate::QuietNotGreater | Predicate::SignalingNotGreater => x != Some(Greater),
+            Predicate::QuietLessUnordered | Predicate::SignalingLessUnordered => {
+                x.is_none() || x == Some(Less)
+            }
+            Predicate::QuietNotLess | Predicate::SignalingNotLess => x != Some(Less),
+            Predicate::QuietGreaterUnordered | Predicate::SignalingGreaterUnordered => {
+                x.is_none() || x == Some(Greater)
+            }
+            Predicate::QuietOrdered => x.is_some(),
+        }
+    }
+}
+
+/// integer type which floating-point values can be converted to
+pub trait Integer: PrimInt {
+    /// Converts `x` to `Self`, see `Float::to_int`
+    fn from_float<F: Float + ?Sized>(x: &F, rnd: RoundingMode, exact: bool) -> Self;
+}
+
+macro_rules! impl_integer {
+    ($t:ty, $to_int:ident) => {
+        impl Integer for $t {
+            #[inline]
+            fn from_float<F: Float + ?Sized>(x: &F, rnd: RoundingMode, exact: bool) -> Self {
+                op::to_narrow_int(x, |x| x.$to_int(rnd, exact))
+            }
+        }
+    };
+}
+
+impl_integer!(u8, to_u32);
+impl_integer!(u16, to_u32);
+impl_integer!(u32, to_u32);
+impl_integer!(u64, to_u64);
+impl_integer!(u128, to_u128);
+impl_integer!(i8, to_i32);
+impl_integer!(i16, to_i32);
+impl_integer!(i32, to_i32);
+impl_integer!(i64, to_i64);
+impl_integer!(i128, to_i128);
+
 /// arbitrary floting-point type
 ///
 /// ## Examples
@@ -218,6 +670,24 @@ pub trait Float {
 
     fn from_i64(x: i64, rnd: RoundingMode) -> Self;
 
+    #[inline]
+    fn from_u128(x: u128, rnd: RoundingMode) -> Self
+    where
+        Self: Sized,
+    {
+        op::from_int(x, || round::round_pack(false, 0, x, false, rnd))
+    }
+
+    #[inline]
+    fn from_i128(x: i128, rnd: RoundingMode) -> Self
+    where
+        Self: Sized,
+    {
+        op::from_int(x as u128, || {
+            round::round_pack(x < 0, 0, x.unsigned_abs(), false, rnd)
+        })
+    }
+
     fn to_u32(&self, rnd: RoundingMode, exact: bool) -> u32;
 
     fn to_u64(&self, rnd: RoundingMode, exact: bool) -> u64;
@@ -226,6 +696,16 @@ pub trait Float {
 
     fn to_i64(&self, rnd: RoundingMode, exact: bool) -> i64;
 
+    #[inline]
+    fn to_u128(&self, rnd: RoundingMode, exact: bool) -> u128 {
+        op::to_wide_int(self, rnd, exact)
+    }
+
+    #[inline]
+    fn to_i128(&self, rnd: RoundingMode, exact: bool) -> i128 {
+        op::to_wide_int(self, rnd, exact)
+    }
+
     fn to_f16(&self, rnd: RoundingMode) -> F16;
 
     fn to_bf16(&self, rnd: RoundingMode) -> BF16;
@@ -238,51 +718,708 @@ pub trait Float {
 
     fn round_to_integral(&self, rnd: RoundingMode) -> Self;
 
+    /// Same as `round_to_integral`, but raises inexact exception if the result differs from `self`
+    #[inline]
+    fn round_to_integral_exact(&self, rnd: RoundingMode) -> Self
+    where
+        Self: Sized,
+    {
+        op::round_to_integral_exact(self, rnd)
+    }
+
+    /// Rounds to integral by the dynamic rounding mode
+    ///
+    /// ## Examples
+    ///
+    /// ```
+    /// use softfloat_wrapper::{Float, RoundingMode, F32};
+    ///
+    /// let a = F32::from_bits(0x3fc00000);
+    /// RoundingMode::TowardPositive.set();
+    /// assert_eq!(a.round_to_integral_dynamic(false).to_bits(), 0x40000000);
+    /// ```
+    #[inline]
+    fn round_to_integral_dynamic(&self, exact: bool) -> Self
+    where
+        Self: Sized,
+    {
+        let rnd = RoundingMode::current();
+        if exact {
+            self.round_to_integral_exact(rnd)
+        } else {
+            self.round_to_integral(rnd)
+        }
+    }
+
+    /// Converts to integer type `I`
+    ///
+    /// It is `convertToIntegerTiesToEven`, `convertToIntegerTowardZero` and so on of standard with `rnd`,
+    /// and `convertToIntegerExact*` if `exact` is set.
+    /// NaN and out-of-range values raise invalid exception without inexact exception,
+    /// and return the integer defined by the specialization.
+    ///
+    /// ## Examples
+    ///
+    /// ```
+    /// use softfloat_wrapper::{Float, RoundingMode, F32};
+    ///
+    /// let a = F32::from_bits(0x43800000);
+    /// let (_, flag) = a.to_int_with_flags::<u8>(RoundingMode::TiesToEven, false);
+    /// assert!(flag.is_invalid());
+    /// let (d, flag) = a.to_int_with_flags::<i16>(RoundingMode::TiesToEven, false);
+    /// assert_eq!(d, 256);
+    /// assert!(!flag.is_invalid());
+    /// ```
+    #[inline]
+    fn to_int<I: Integer>(&self, rnd: RoundingMode, exact: bool) -> I {
+        I::from_float(self, rnd, exact)
+    }
+
+    /// Compares `self` and `x`, and raises invalid exception for any NaN
+    #[inline]
+    fn compare<T: Borrow<Self>>(&self, x: T) -> Option<Ordering> {
+        let x = x.borrow();
+        trap::check(Operation::Compare, &[op::bits(self), op::bits(x)], || {
+            let eq = self.eq(x);
+            let lt = self.lt(x);
+            if self.is_nan() || x.is_nan() {
+                None
+            } else if eq {
+                Some(Ordering::Equal)
+            } else if lt {
+                Some(Ordering::Less)
+            } else {
+                Some(Ordering::Greater)
+            }
+        })
+    }
+
+    /// minimum of IEEE 754-2019, which propagates NaN
+    #[inline]
+    fn minimum<T: Borrow<Self>>(&self, x: T) -> Self
+    where
+        Self: Sized,
+    {
+        op::min_max(self, x.borrow(), false, false, false)
+    }
+
+    /// maximum of IEEE 754-2019, which propagates NaN
+    #[inline]
+    fn maximum<T: Borrow<Self>>(&self, x: T) -> Self
+    where
+        Self: Sized,
+    {
+        op::min_max(self, x.borrow(), true, false, false)
+    }
+
+    /// minimumNumber of IEEE 754-2019, which returns the number if one operand is NaN
+    #[inline]
+    fn minimum_number<T: Borrow<Self>>(&self, x: T) -> Self
+    where
+        Self: Sized,
+    {
+        op::min_max(self, x.borrow(), false, false, true)
+    }
+
+    /// maximumNumber of IEEE 754-2019, which returns the number if one operand is NaN
+    #[inline]
+    fn maximum_number<T: Borrow<Self>>(&self, x: T) -> Self
+    where
+        Self: Sized,
+    {
+        op::min_max(self, x.borrow(), true, false, true)
+    }
+
+    /// minimumMagnitude of IEEE 754-2019
+    #[inline]
+    fn minimum_magnitude<T: Borrow<Self>>(&self, x: T) -> Self
+    where
+        Self: Sized,
+    {
+        op::min_max(self, x.borrow(), false, true, false)
+    }
+
+    /// maximumMagnitude of IEEE 754-2019
+    #[inline]
+    fn maximum_magnitude<T: Borrow<Self>>(&self, x: T) -> Self
+    where
+        Self: Sized,
+    {
+        op::min_max(self, x.borrow(), true, true, false)
+    }
+
+    /// minimumMagnitudeNumber of IEEE 754-2019
+    #[inline]
+    fn minimum_magnitude_number<T: Borrow<Self>>(&self, x: T) -> Self
+    where
+        Self: Sized,
+    {
+        op::min_max(self, x.borrow(), false, true, true)
+    }
+
+    /// maximumMagnitudeNumber of IEEE 754-2019
+    #[inline]
+    fn maximum_magnitude_number<T: Borrow<Self>>(&self, x: T) -> Self
+    where
+        Self: Sized,
+    {
+        op::min_max(self, x.borrow(), true, true, true)
+    }
+
+    /// least value which is greater than `self`
+    #[inline]
+    fn next_up(&self) -> Self
+    where
+        Self: Sized,
+    {
+        trap::check(Operation::NextAfter, &[op::bits(self)], || {
+            if self.is_nan() {
+                return op::propagate_nan(self, &Self::positive_zero());
+            }
+            let bits = self.to_bits();
+            if self.is_zero() {
+                Self::from_bits(Self::Payload::one())
+            } else if self.is_positive_infinity() {
+                Self::from_bits(bits)
+            } else if self.is_positive() {
+                Self::from_bits(bits + Self::Payload::one())
+            } else {
+                Self::from_bits(bits - Self::Payload::one())
+            }
+        })
+    }
+
+    /// greatest value which is less than `self`
+    #[inline]
+    fn next_down(&self) -> Self
+    where
+        Self: Sized,
+    {
+        if self.is_nan() {
+            return self.next_up();
+        }
+        trap::check(Operation::NextAfter, &[op::bits(self)], || {
+            self.neg().next_up().neg()
+        })
+    }
+
+    /// next value after `self` in the direction of `x`, or `x` if they are equal
+    #[inline]
+    fn next_after<T: Borrow<Self>>(&self, x: T) -> Self
+    where
+        Self: Sized,
+    {
+        let x = x.borrow();
+        trap::check(Operation::NextAfter, &[op::bits(self), op::bits(x)], || {
+            if self.is_nan() || x.is_nan() {
+                op::propagate_nan(self, x)
+            } else if self.lt_quiet(x) {
+                self.next_up()
+            } else if x.lt_quiet(self) {
+                self.next_down()
+            } else {
+                Self::from_bits(x.to_bits())
+            }
+        })
+    }
+
+    /// distance between `self.abs()` and the next value toward infinity in the exponent range of `self`
+    #[inline]
+    fn ulp(&self) -> Self
+    where
+        Self: Sized,
+    {
+        if self.is_nan() {
+            return trap::check(Operation::Ulp, &[op::bits(self)], || {
+                op::propagate_nan(self, &Self::positive_zero())
+            });
+        }
+        if self.exponent() == Self::EXPONENT_BIT {
+            return Self::positive_infinity();
+        }
+        let exponent = self.exponent().max(Self::Payload::one());
+        let pos = <Self::Payload as num_traits::NumCast>::from(Self::EXPONENT_POS).unwrap();
+        if exponent > pos {
+            Self::from_bits((exponent - pos) << Self::EXPONENT_POS)
+        } else {
+            let shift =
+                num_traits::ToPrimitive::to_usize(&(exponent - Self::Payload::one())).unwrap();
+            Self::from_bits(Self::Payload::one() << shift)
+        }
+    }
+
+    /// number of representable values between `self` and `x`, or the maximum value of `Payload` if either is NaN
+    #[inline]
+    fn ulp_distance<T: Borrow<Self>>(&self, x: T) -> Self::Payload
+    where
+        Self: Sized,
+    {
+        let x = x.borrow();
+        if self.is_nan() || x.is_nan() {
+            return <Self::Payload as num_traits::Bounded>::max_value();
+        }
+        let magnitude = |x: &Self| x.abs().to_bits();
+        let (a, b) = (magnitude(self), magnitude(x));
+        if a == Self::Payload::zero() || b == Self::Payload::zero() || self.sign() != x.sign() {
+            a + b
+        } else if a > b {
+            a - b
+        } else {
+            b - a
+        }
+    }
+
+    /// Returns `(sign, exponent, significand)` of finite value, where the value is `(-1)^sign * significand * 2^exponent`
+    #[inline]
+    fn decompose(&self) -> Option<(bool, i32, u128)> {
+        if self.exponent() == Self::EXPONENT_BIT {
+            return None;
+        }
+        let bias = num_traits::ToPrimitive::to_i32(&Self::EXPONENT_BIT).unwrap() >> 1;
+        let pos = Self::EXPONENT_POS as i32;
+        let exponent = num_traits::ToPrimitive::to_i32(&self.exponent()).unwrap();
+        let fraction = num_traits::ToPrimitive::to_u128(&self.fraction()).unwrap();
+        let sign = self.is_negative();
+        if exponent == 0 {
+            Some((sign, 1 - bias - pos, fraction))
+        } else {
+            Some((sign, exponent - bias - pos, fraction | (1 << pos)))
+        }
+    }
+
+    /// Rounds `(-1)^sign * significand * 2^exponent` by `rnd`
+    #[inline]
+    fn compose(sign: bool, exponent: i32, significand: u128, rnd: RoundingMode) -> Self
+    where
+        Self: Sized,
+    {
+        let operands = [sign as u128, exponent as i128 as u128, significand];
+        trap::check(Operation::Compose, &operands, || {
+            flush::output(|| round::round_pack(sign, exponent, significand, false, rnd))
+        })
+    }
+
+    /// `self * 2^n` rounded by `rnd`
+    #[inline]
+    fn scale_b(&self, n: i32, rnd: RoundingMode) -> Self
+    where
+        Self: Sized,
+    {
+        let operands = [op::bits(self), n as i128 as u128];
+        trap::check(Operation::ScaleB, &operands, || {
+            if self.is_nan() {
+                return op::propagate_nan(self, &Self::positive_zero());
+            }
+            let a = flush::input(self);
+            flush::output(|| match a.decompose() {
+                Some((sign, e, f)) if f != 0 => {
+                    // clamp to avoid overflow, which doesn't change the result
+                    let e = e.saturating_add(n).clamp(-(1 << 20), 1 << 20);
+                    round::round_pack(sign, e, f, false, rnd)
+                }
+                _ => Self::from_bits(a.to_bits()),
+            })
+        })
+    }
+
+    /// Same as `scale_b`
+    #[inline]
+    fn ldexp(&self, n: i32, rnd: RoundingMode) -> Self
+    where
+        Self: Sized,
+    {
+        self.scale_b(n, rnd)
+    }
+
+    /// unbiased exponent of `self`, where subnormals are normalized
+    ///
+    /// `i32::MIN` for zero and NaN, and `i32::MAX` for infinity are returned with invalid exception.
+    #[inline]
+    fn log_b(&self) -> i32 {
+        trap::check(Operation::LogB, &[op::bits(self)], || {
+            match self.decompose() {
+                Some((_, e, f)) if f != 0 => e + 127 - f.leading_zeros() as i32,
+                _ => {
+                    unsafe {
+                        softfloat_sys::softfloat_raiseFlags(softfloat_sys::softfloat_flag_invalid)
+                    };
+                    if self.is_nan() || self.is_zero() {
+                        i32::MIN
+                    } else {
+                        i32::MAX
+                    }
+                }
+            }
+        })
+    }
+
+    /// Returns `(m, e)`, where `self = m * 2^e` and `0.5 <= |m| < 1`
+    ///
+    /// Zero, infinity and NaN are returned as is with exponent 0.
+    #[inline]
+    fn frexp(&self) -> (Self, i32)
+    where
+        Self: Sized,
+    {
+        match self.decompose() {
+            Some((sign, e, f)) if f != 0 => {
+                let n = 128 - f.leading_zeros() as i32;
+                let m = round::round_pack(sign, -n, f, false, RoundingMode::TiesToEven);
+                (m, e + n)
+            }
+            _ => (Self::from_bits(self.to_bits()), 0),
+        }
+    }
+
+    /// totalOrder of IEEE 754, which never signals
+    #[inline]
+    fn total_order<T: Borrow<Self>>(&self, x: T) -> bool {
+        total_ord::key(self) <= total_ord::key(x.borrow())
+    }
+
+    /// totalOrderMag of IEEE 754, which never signals
+    #[inline]
+    fn total_order_mag<T: Borrow<Self>>(&self, x: T) -> bool
+    where
+        Self: Sized,
+    {
+        self.abs().total_order(x.borrow().abs())
+    }
+
+    /// Same as `compare`, but raises invalid exception only for signaling NaN
+    #[inline]
+    fn compare_quiet<T: Borrow<Self>>(&self, x: T) -> Option<Ordering> {
+        let x = x.borrow();
+        trap::check(Operation::Compare, &[op::bits(self), op::bits(x)], || {
+            if self.is_nan() || x.is_nan() {
+                if self.is_signaling_nan() || x.is_signaling_nan() {
+                    unsafe {
+                        softfloat_sys::softfloat_raiseFlags(softfloat_sys::softfloat_flag_invalid)
+                    };
+                }
+                None
+            } else if self.lt_quiet(x) {
+                Some(Ordering::Less)
+            } else if self.eq(x) {
+                Some(Ordering::Equal)
+            } else {
+                Some(Ordering::Greater)
+            }
+        })
+    }
+
+    /// Tests `self` and `x` by `predicate`
+    #[inline]
+    fn compare_predicate<T: Borrow<Self>>(&self, x: T, predicate: Predicate) -> bool {
+        let x = x.borrow();
+        trap::check(Operation::Compare, &[op::bits(self), op::bits(x)], || {
+            let ret = self.compare_quiet(x);
+            if ret.is_none() && predicate.is_signaling() {
+                unsafe {
+                    softfloat_sys::softfloat_raiseFlags(softfloat_sys::softfloat_flag_invalid)
+                };
+            }
+            predicate.test(ret)
+        })
+    }
+
+    #[inline]
+    fn from_u8(x: u8, rnd: RoundingMode) -> Self
+    where
+        Self: Sized,
+    {
+        Self::from_u32(x as u32, rnd)
+    }
+
+    #[inline]
+    fn from_u16(x: u16, rnd: RoundingMode) -> Self
+    where
+        Self: Sized,
+    {
+        Self::from_u32(x as u32, rnd)
+    }
+
+    #[inline]
+    fn from_i8(x: i8, rnd: RoundingMode) -> Self
+    where
+        Self: Sized,
+    {
+        Self::from_i32(x as i32, rnd)
+    }
+
+    #[inline]
+    fn from_i16(x: i16, rnd: RoundingMode) -> Self
+    where
+        Self: Sized,
+    {
+        Self::from_i32(x as i32, rnd)
+    }
+
+    #[inline]
+    fn add_with_flags<T: Borrow<Self>>(&self, x: T, rnd: RoundingMode) -> (Self, ExceptionFlags)
+    where
+        Self: Sized,
+    {
+        ExceptionFlags::capture(|| self.add(x, rnd))
+    }
+
+    #[inline]
+    fn sub_with_flags<T: Borrow<Self>>(&self, x: T, rnd: RoundingMode) -> (Self, ExceptionFlags)
+    where
+        Self: Sized,
+    {
+        ExceptionFlags::capture(|| self.sub(x, rnd))
+    }
+
+    #[inline]
+    fn mul_with_flags<T: Borrow<Self>>(&self, x: T, rnd: RoundingMode) -> (Self, ExceptionFlags)
+    where
+        Self: Sized,
+    {
+        ExceptionFlags::capture(|| self.mul(x, rnd))
+    }
+
+    #[inline]
+    fn fused_mul_add_with_flags<T: Borrow<Self>>(
+        &self,
+        x: T,
+        y: T,
+        rnd: RoundingMode,
+    ) -> (Self, ExceptionFlags)
+    where
+        Self: Sized,
+    {
+        ExceptionFlags::capture(|| self.fused_mul_add(x, y, rnd))
+    }
+
+    #[inline]
+    fn div_with_flags<T: Borrow<Self>>(&self, x: T, rnd: RoundingMode) -> (Self, ExceptionFlags)
+    where
+        Self: Sized,
+    {
+        ExceptionFlags::capture(|| self.div(x, rnd))
+    }
+
+    #[inline]
+    fn rem_with_flags<T: Borrow<Self>>(&self, x: T, rnd: RoundingMode) -> (Self, ExceptionFlags)
+    where
+        Self: Sized,
+    {
+        ExceptionFlags::capture(|| self.rem(x, rnd))
+    }
+
+    #[inline]
+    fn sqrt_with_flags(&self, rnd: RoundingMode) -> (Self, ExceptionFlags)
+    where
+        Self: Sized,
+    {
+        ExceptionFlags::capture(|| self.sqrt(rnd))
+    }
+
+    #[inline]
+    fn eq_with_flags<T: Borrow<Self>>(&self, x: T) -> (bool, ExceptionFlags) {
+        ExceptionFlags::capture(|| self.eq(x))
+    }
+
+    #[inline]
+    fn lt_with_flags<T: Borrow<Self>>(&self, x: T) -> (bool, ExceptionFlags) {
+        ExceptionFlags::capture(|| self.lt(x))
+    }
+
+    #[inline]
+    fn le_with_flags<T: Borrow<Self>>(&self, x: T) -> (bool, ExceptionFlags) {
+        ExceptionFlags::capture(|| self.le(x))
+    }
+
+    #[inline]
+    fn lt_quiet_with_flags<T: Borrow<Self>>(&self, x: T) -> (bool, ExceptionFlags) {
+        ExceptionFlags::capture(|| self.lt_quiet(x))
+    }
+
+    #[inline]
+    fn le_quiet_with_flags<T: Borrow<Self>>(&self, x: T) -> (bool, ExceptionFlags) {
+        ExceptionFlags::capture(|| self.le_quiet(x))
+    }
+
+    #[inline]
+    fn eq_signaling_with_flags<T: Borrow<Self>>(&self, x: T) -> (bool, ExceptionFlags) {
+        ExceptionFlags::capture(|| self.eq_signaling(x))
+    }
+
+    #[inline]
+    fn compare_with_flags<T: Borrow<Self>>(&self, x: T) -> (Option<Ordering>, ExceptionFlags) {
+        ExceptionFlags::capture(|| self.compare(x))
+    }
+
+    #[inline]
+    fn from_u8_with_flags(x: u8, rnd: RoundingMode) -> (Self, ExceptionFlags)
+    where
+        Self: Sized,
+    {
+        ExceptionFlags::capture(|| Self::from_u8(x, rnd))
+    }
+
+    #[inline]
+    fn from_u16_with_flags(x: u16, rnd: RoundingMode) -> (Self, ExceptionFlags)
+    where
+        Self: Sized,
+    {
+        ExceptionFlags::capture(|| Self::from_u16(x, rnd))
+    }
+
+    #[inline]
+    fn from_u32_with_flags(x: u32, rnd: RoundingMode) -> (Self, ExceptionFlags)
+    where
+        Self: Sized,
+    {
+        ExceptionFlags::capture(|| Self::from_u32(x, rnd))
+    }
+
+    #[inline]
+    fn from_u64_with_flags(x: u64, rnd: RoundingMode) -> (Self, ExceptionFlags)
+    where
+        Self: Sized,
+    {
+        ExceptionFlags::capture(|| Self::from_u64(x, rnd))
+    }
+
+    #[inline]
+    fn from_i8_with_flags(x: i8, rnd: RoundingMode) -> (Self, ExceptionFlags)
+    where
+        Self: Sized,
+    {
+        ExceptionFlags::capture(|| Self::from_i8(x, rnd))
+    }
+
+    #[inline]
+    fn from_i16_with_flags(x: i16, rnd: RoundingMode) -> (Self, ExceptionFlags)
+    where
+        Self: Sized,
+    {
+        ExceptionFlags::capture(|| Self::from_i16(x, rnd))
+    }
+
+    #[inline]
+    fn from_i32_with_flags(x: i32, rnd: RoundingMode) -> (Self, ExceptionFlags)
+    where
+        Self: Sized,
+    {
+        ExceptionFlags::capture(|| Self::from_i32(x, rnd))
+    }
+
+    #[inline]
+    fn from_i64_with_flags(x: i64, rnd: RoundingMode) -> (Self, ExceptionFlags)
+    where
+        Self: Sized,
+    {
+        ExceptionFlags::capture(|| Self::from_i64(x, rnd))
+    }
+
+    #[inline]
+    fn from_u128_with_flags(x: u128, rnd: RoundingMode) -> (Self, ExceptionFlags)
+    where
+        Self: Sized,
+    {
+        ExceptionFlags::capture(|| Self::from_u128(x, rnd))
+    }
+
+    #[inline]
+    fn from_i128_with_flags(x: i128, rnd: RoundingMode) -> (Self, ExceptionFlags)
+    where
+        Self: Sized,
+    {
+        ExceptionFlags::capture(|| Self::from_i128(x, rnd))
+    }
+
+    #[inline]
+    fn to_u32_with_flags(&self, rnd: RoundingMode, exact: bool) -> (u32, ExceptionFlags) {
+        ExceptionFlags::capture(|| self.to_u32(rnd, exact))
+    }
+
+    #[inline]
+    fn to_u64_with_flags(&self, rnd: RoundingMode, exact: bool) -> (u64, ExceptionFlags) {
+        ExceptionFlags::capture(|| self.to_u64(rnd, exact))
+    }
+
+    #[inline]
+    fn to_i32_with_flags(&self, rnd: RoundingMode, exact: bool) -> (i32, ExceptionFlags) {
+        ExceptionFlags::capture(|| self.to_i32(rnd, exact))
+    }
+
+    #[inline]
+    fn to_i64_with_flags(&self, rnd: RoundingMode, exact: bool) -> (i64, ExceptionFlags) {
+        ExceptionFlags::capture(|| self.to_i64(rnd, exact))
+    }
+
+    #[inline]
+    fn to_u128_with_flags(&self, rnd: RoundingMode, exact: bool) -> (u128, ExceptionFlags) {
+        ExceptionFlags::capture(|| self.to_u128(rnd, exact))
+    }
+
+    #[inline]
+    fn to_i128_with_flags(&self, rnd: RoundingMode, exact: bool) -> (i128, ExceptionFlags) {
+        ExceptionFlags::capture(|| self.to_i128(rnd, exact))
+    }
+
+    #[inline]
+    fn to_int_with_flags<I: Integer>(&self, rnd: RoundingMode, exact: bool) -> (I, ExceptionFlags) {
+        ExceptionFlags::capture(|| self.to_int(rnd, exact))
+    }
+
+    #[inline]
+    fn to_f16_with_flags(&self, rnd: RoundingMode) -> (F16, ExceptionFlags) {
+        ExceptionFlags::capture(|| self.to_f16(rnd))
+    }
+
+    #[inline]
+    fn to_bf16_with_flags(&self, rnd: RoundingMode) -> (BF16, ExceptionFlags) {
+        ExceptionFlags::capture(|| self.to_bf16(rnd))
+    }
+
+    #[inline]
+    fn to_f32_with_flags(&self, rnd: RoundingMode) -> (F32, ExceptionFlags) {
+        ExceptionFlags::capture(|| self.to_f32(rnd))
+    }
+
     #[inline]
-    fn compare<T: Borrow<Self>>(&self, x: T) -> Option<Ordering> {
-        let eq = self.eq(x.borrow());
-        let lt = self.lt(x.borrow());
-        if self.is_nan() || x.borrow().is_nan() {
-            None
-        } else if eq {
-            Some(Ordering::Equal)
-        } else if lt {
-            Some(Ordering::Less)
-        } else {
-            Some(Ordering::Greater)
-        }
+    fn to_f64_with_flags(&self, rnd: RoundingMode) -> (F64, ExceptionFlags) {
+        ExceptionFlags::capture(|| self.to_f64(rnd))
     }
 
     #[inline]
-    fn from_u8(x: u8, rnd: RoundingMode) -> Self
-    where
-        Self: Sized,
-    {
-        Self::from_u32(x as u32, rnd)
+    fn to_f128_with_flags(&self, rnd: RoundingMode) -> (F128, ExceptionFlags) {
+        ExceptionFlags::capture(|| self.to_f128(rnd))
     }
 
     #[inline]
-    fn from_u16(x: u16, rnd: RoundingMode) -> Self
+    fn round_to_integral_with_flags(&self, rnd: RoundingMode) -> (Self, ExceptionFlags)
     where
         Self: Sized,
     {
-        Self::from_u32(x as u32, rnd)
+        ExceptionFlags::capture(|| self.round_to_integral(rnd))
     }
 
     #[inline]
-    fn from_i8(x: i8, rnd: RoundingMode) -> Self
+    fn round_to_integral_exact_with_flags(&self, rnd: RoundingMode) -> (Self, ExceptionFlags)
     where
         Self: Sized,
     {
-        Self::from_i32(x as i32, rnd)
+        ExceptionFlags::capture(|| self.round_to_integral_exact(rnd))
     }
 
-    #[inline]
-    fn from_i16(x: i16, rnd: RoundingMode) -> Self
+    /// Parses a decimal or hexadecimal (`0x1.8p3`) string with correct rounding
+    ///
+    /// `inf`, `infinity`, `nan`, `snan` and NaN payload like `nan(0x1234)` are accepted.
+    fn from_str_rounded(
+        s: &str,
+        rnd: RoundingMode,
+    ) -> Result<(Self, ExceptionFlags), ParseFloatError>
     where
         Self: Sized,
     {
-        Self::from_i32(x as i32, rnd)
+        let (ret, flag) =
+            ExceptionFlags::capture(|| trap::check(Operation::Parse, &[], || parse::parse(s, rnd)));
+        ret.map(|x| (x, flag))
     }
 
     #[inline]
@@ -398,7 +1535,64 @@ pub trait Float {
 
     #[inline]
     fn is_subnormal(&self) -> bool {
-        self.exponent() == Self::Payload::zero()
+        self.exponent() == Self::Payload::zero() && self.fraction() != Self::Payload::zero()
+    }
+
+    #[inline]
+    fn is_normal(&self) -> bool {
+        self.exponent() != Self::Payload::zero() && self.exponent() != Self::EXPONENT_BIT
+    }
+
+    #[inline]
+    fn is_finite(&self) -> bool {
+        self.exponent() != Self::EXPONENT_BIT
+    }
+
+    #[inline]
+    fn is_infinite(&self) -> bool {
+        self.exponent() == Self::EXPONENT_BIT && self.fraction() == Self::Payload::zero()
+    }
+
+    #[inline]
+    fn is_quiet_nan(&self) -> bool {
+        self.is_nan() && !self.is_signaling_nan()
+    }
+
+    /// Always true because all encodings of binary formats are canonical
+    #[inline]
+    fn is_canonical(&self) -> bool {
+        true
+    }
+
+    #[inline]
+    fn class(&self) -> FloatClass {
+        if self.is_signaling_nan() {
+            FloatClass::SignalingNaN
+        } else if self.is_nan() {
+            FloatClass::QuietNaN
+        } else if self.is_negative_infinity() {
+            FloatClass::NegativeInfinity
+        } else if self.is_negative_normal() {
+            FloatClass::NegativeNormal
+        } else if self.is_negative_subnormal() {
+            FloatClass::NegativeSubnormal
+        } else if self.is_negative_zero() {
+            FloatClass::NegativeZero
+        } else if self.is_positive_zero() {
+            FloatClass::PositiveZero
+        } else if self.is_positive_subnormal() {
+            FloatClass::PositiveSubnormal
+        } else if self.is_positive_normal() {
+            FloatClass::PositiveNormal
+        } else {
+            FloatClass::PositiveInfinity
+        }
+    }
+
+    /// Returns the 10-bit result of RISC-V `fclass` instruction
+    #[inline]
+    fn fclass_mask(&self) -> u16 {
+        self.class().fclass_mask()
     }
 
     #[inline]
@@ -472,6 +1666,82 @@ pub trait Float {
         x.set_fraction(Self::Payload::one() << (Self::EXPONENT_POS - 1));
         x
     }
+
+    /// signaling NaN with the most significant bit of payload
+    #[inline]
+    fn signaling_nan() -> Self
+    where
+        Self: Sized,
+    {
+        let mut x = Self::from_bits(Self::Payload::zero());
+        x.set_exponent(Self::EXPONENT_BIT);
+        x.set_fraction(Self::Payload::one() << (Self::EXPONENT_POS - 2));
+        x
+    }
+
+    /// getPayload of IEEE 754-2019, which returns -1 if `self` is not NaN
+    #[inline]
+    fn get_payload(&self) -> Self
+    where
+        Self: Sized,
+    {
+        if self.is_nan() {
+            let quiet_bit = Self::Payload::one() << (Self::EXPONENT_POS - 1);
+            let payload = self.fraction() & !quiet_bit;
+            let payload = num_traits::ToPrimitive::to_u128(&payload).unwrap();
+            Self::compose(false, 0, payload, RoundingMode::TiesToEven)
+        } else {
+            Self::compose(true, 0, 1, RoundingMode::TiesToEven)
+        }
+    }
+
+    /// setPayload of IEEE 754-2019, which returns quiet NaN with payload `x`, or +0 if `x` is not a valid payload
+    #[inline]
+    fn from_payload<T: Borrow<Self>>(x: T) -> Self
+    where
+        Self: Sized,
+    {
+        match op::payload(x.borrow()) {
+            Some(payload) => {
+                let mut ret = Self::quiet_nan();
+                ret.set_fraction(ret.fraction() | payload);
+                ret
+            }
+            None => Self::positive_zero(),
+        }
+    }
+
+    /// setPayloadSignaling of IEEE 754-2019, which returns signaling NaN with payload `x`, or +0 if `x` is not a valid payload
+    #[inline]
+    fn from_payload_signaling<T: Borrow<Self>>(x: T) -> Self
+    where
+        Self: Sized,
+    {
+        match op::payload(x.borrow()) {
+            Some(payload) if payload != Self::Payload::zero() => {
+                let mut ret = Self::positive_infinity();
+                ret.set_fraction(payload);
+                ret
+            }
+            _ => Self::positive_zero(),
+        }
+    }
+
+    /// Returns the default NaN of the active specialization if `self` is NaN
+    #[inline]
+    fn canonicalize(&self) -> Self
+    where
+        Self: Sized,
+    {
+        if self.is_nan() {
+            trap::check(Operation::Canonicalize, &[op::bits(self)], || {
+                op::signal_nan(self, self);
+                Specialization::current().default_nan()
+            })
+        } else {
+            Self::from_bits(self.to_bits())
+        }
+    }
 }
 
 #[cfg(test)]
@@ -562,4 +1832,938 @@ mod tests {
         assert!(!flag.is_overflow());
         assert!(flag.is_underflow());
     }
+
+    #[test]
+    fn flag_with_flags() {
+        let a = F16::from_bits(0x1234);
+        let b = F16::from_bits(0x7654);
+        let mut flag = ExceptionFlags::default();
+        flag.set();
+        let (d, flag0) = a.add_with_flags(b, RoundingMode::TiesToEven);
+        flag.get();
+        assert_eq!(d.to_bits(), a.add(b, RoundingMode::TiesToEven).to_bits());
+        assert!(flag0.is_inexact());
+        assert!(!flag.is_inexact());
+
+        let a = F16::from_bits(0x0);
+        let (d, flag0) = a.div_with_flags(a, RoundingMode::TiesToEven);
+        assert!(d.is_nan());
+        assert!(flag0.is_invalid());
+        assert!(!flag0.is_inexact());
+
+        let a = F16::from_bits(0x7e00);
+        let (d, flag0) = a.lt_with_flags(a);
+        assert!(!d);
+        assert!(flag0.is_invalid());
+        let (d, flag0) = a.lt_quiet_with_flags(a);
+        assert!(!d);
+        assert!(!flag0.is_invalid());
+    }
+
+    #[test]
+    fn flag_thread_local() {
+        use std::sync::{Arc, Barrier};
+        use std::thread;
+
+        let barrier = Arc::new(Barrier::new(2));
+
+        let b0 = barrier.clone();
+        let t0 = thread::spawn(move || {
+            let z = F16::from_bits(0x0);
+            ExceptionFlags::default().set();
+            for _ in 0..1000 {
+                let _d = z.div(z, RoundingMode::TiesToEven);
+            }
+            b0.wait();
+            b0.wait();
+            let mut flag = ExceptionFlags::default();
+            flag.get();
+            flag.to_bits()
+        });
+
+        let b1 = barrier;
+        let t1 = thread::spawn(move || {
+            ExceptionFlags::default().set();
+            b1.wait();
+            let mut flag = ExceptionFlags::default();
+            flag.get();
+            let before = flag.to_bits();
+            let a = F16::from_bits(0x1234);
+            let b = F16::from_bits(0x7654);
+            let _d = a.add(b, RoundingMode::TiesToEven);
+            b1.wait();
+            flag.get();
+            (before, flag.to_bits())
+        });
+
+        let flag0 = ExceptionFlags::from_bits(t0.join().unwrap());
+        let (before, after) = t1.join().unwrap();
+        let flag1 = ExceptionFlags::from_bits(after);
+        assert!(flag0.is_invalid());
+        assert!(!flag0.is_inexact());
+        assert_eq!(before, 0);
+        assert!(flag1.is_inexact());
+        assert!(!flag1.is_invalid());
+    }
+
+    #[test]
+    fn rounding_mode_thread_local() {
+        use std::sync::{Arc, Barrier};
+        use std::thread;
+
+        let modes = [
+            RoundingMode::TiesToEven,
+            RoundingMode::TowardZero,
+            RoundingMode::TowardNegative,
+            RoundingMode::TowardPositive,
+            RoundingMode::TiesToAway,
+            RoundingMode::ToOdd,
+        ];
+        let barrier = Arc::new(Barrier::new(modes.len()));
+
+        let threads: Vec<_> = modes
+            .iter()
+            .map(|rnd| {
+                let rnd = *rnd;
+                let barrier = barrier.clone();
+                thread::spawn(move || {
+                    rnd.set();
+                    barrier.wait();
+                    let mut ok = true;
+                    for _ in 0..1000 {
+                        let mode = unsafe { softfloat_sys::softfloat_roundingMode_read_helper() };
+                        ok &= mode == rnd.to_softfloat();
+                        rnd.set();
+                    }
+                    ok
+                })
+            })
+            .collect();
+
+        for t in threads {
+            assert!(t.join().unwrap());
+        }
+    }
+
+    #[test]
+    fn min_max() {
+        let f = |x: f32| F32::from_bits(x.to_bits());
+        let (a, b) = (f(1.0), f(-2.0));
+        assert_eq!(a.minimum(b).to_bits(), b.to_bits());
+        assert_eq!(a.maximum(b).to_bits(), a.to_bits());
+        assert_eq!(a.minimum_magnitude(b).to_bits(), a.to_bits());
+        assert_eq!(a.maximum_magnitude(b).to_bits(), b.to_bits());
+        assert_eq!(a.minimum_number(b).to_bits(), b.to_bits());
+        assert_eq!(a.maximum_magnitude_number(b).to_bits(), b.to_bits());
+
+        let (p, n) = (f(0.0), f(-0.0));
+        assert_eq!(p.minimum(n).to_bits(), n.to_bits());
+        assert_eq!(n.minimum(p).to_bits(), n.to_bits());
+        assert_eq!(p.maximum(n).to_bits(), p.to_bits());
+        assert_eq!(n.maximum_number(p).to_bits(), p.to_bits());
+        let (a, b) = (f(2.0), f(-2.0));
+        assert_eq!(a.minimum_magnitude(b).to_bits(), b.to_bits());
+        assert_eq!(b.maximum_magnitude(a).to_bits(), a.to_bits());
+        assert_eq!(a.minimum_magnitude_number(b).to_bits(), b.to_bits());
+    }
+
+    #[test]
+    fn min_max_nan() {
+        let a = F32::from_bits(0x3f800000);
+        let q = F32::from_bits(0x7fc01234);
+        let s = F32::from_bits(0x7f804321);
+
+        let (d, flag) = ExceptionFlags::capture(|| a.minimum(q));
+        assert_eq!(d.to_bits(), 0x7fc01234);
+        assert!(!flag.is_invalid());
+        let (d, flag) = ExceptionFlags::capture(|| q.maximum_number(a));
+        assert_eq!(d.to_bits(), 0x3f800000);
+        assert!(!flag.is_invalid());
+        let (d, flag) = ExceptionFlags::capture(|| a.maximum(s));
+        assert_eq!(d.to_bits(), 0x7fc04321);
+        assert!(flag.is_invalid());
+        let (d, flag) = ExceptionFlags::capture(|| s.minimum_magnitude_number(a));
+        assert_eq!(d.to_bits(), 0x3f800000);
+        assert!(flag.is_invalid());
+        let (d, flag) = ExceptionFlags::capture(|| q.minimum_number(q));
+        assert_eq!(d.to_bits(), 0x7fc01234);
+        assert!(!flag.is_invalid());
+    }
+
+    #[test]
+    fn min_max_specialization() {
+        let q = F32::from_bits(0x7fc01234);
+        let s = F32::from_bits(0x7f804321);
+        let d = Specialization::RiscV.scope(|| q.minimum_number(s));
+        assert_eq!(d.to_bits(), 0x7fc00000);
+        let d = Specialization::X86Sse.scope(|| q.minimum_number(s));
+        assert_eq!(d.to_bits(), 0x7fc01234);
+        let d = Specialization::ArmVfpv2.scope(|| q.maximum(s));
+        assert_eq!(d.to_bits(), 0x7fc04321);
+    }
+
+    #[test]
+    fn next_up_down() {
+        let values = [
+            (0x0000, 0x0001, 0x8001),
+            (0x8000, 0x0001, 0x8001),
+            (0x0001, 0x0002, 0x0000),
+            (0x8001, 0x8000, 0x8002),
+            (0x03ff, 0x0400, 0x03fe),
+            (0x3c00, 0x3c01, 0x3bff),
+            (0xbc00, 0xbbff, 0xbc01),
+            (0x7bff, 0x7c00, 0x7bfe),
+            (0x7c00, 0x7c00, 0x7bff),
+            (0xfc00, 0xfbff, 0xfc00),
+        ];
+        for (x, up, down) in values.iter() {
+            let x = F16::from_bits(*x);
+            assert_eq!(x.next_up().to_bits(), *up);
+            assert_eq!(x.next_down().to_bits(), *down);
+        }
+
+        let (d, flag) = ExceptionFlags::capture(|| F16::from_bits(0x7d00).next_up());
+        assert_eq!(d.to_bits(), 0x7f00);
+        assert!(flag.is_invalid());
+        let (d, flag) = ExceptionFlags::capture(|| F16::from_bits(0xfe00).next_down());
+        assert_eq!(d.to_bits(), 0xfe00);
+        assert!(!flag.is_invalid());
+
+        // NaN is propagated without flipping the sign, same as next_up
+        for spec in [Specialization::RiscV, Specialization::ArmVfpv2DefaultNaN] {
+            let a = F32::from_bits(0x7fc00001);
+            let b = F32::from_bits(0xffc00001);
+            spec.scope(|| {
+                assert_eq!(a.next_up().to_bits(), 0x7fc00000);
+                assert_eq!(a.next_down().to_bits(), 0x7fc00000);
+                assert_eq!(b.next_down().to_bits(), 0x7fc00000);
+            });
+        }
+        let d = Specialization::X86Sse.scope(|| F32::from_bits(0xffc00001).next_down());
+        assert_eq!(d.to_bits(), 0xffc00001);
+
+        let a = F128::from_bits(0x3fff << 112);
+        assert_eq!(a.next_up().to_bits(), (0x3fff << 112) + 1);
+        assert_eq!(a.next_down().to_bits(), (0x3fff << 112) - 1);
+    }
+
+    #[test]
+    fn next_after() {
+        let a = F32::from_bits(0x3f800000);
+        let b = F32::from_bits(0x40000000);
+        assert_eq!(a.next_after(b).to_bits(), 0x3f800001);
+        assert_eq!(b.next_after(a).to_bits(), 0x3fffffff);
+        assert_eq!(a.next_after(a).to_bits(), 0x3f800000);
+        let p = F32::from_bits(0x00000000);
+        let n = F32::from_bits(0x80000000);
+        assert_eq!(p.next_after(n).to_bits(), 0x80000000);
+        assert_eq!(p.next_after(a).to_bits(), 0x00000001);
+        assert!(a.next_after(F32::quiet_nan()).is_nan());
+    }
+
+    #[test]
+    fn ulp() {
+        let values = [
+            (0x3f800000, 0x34000000),
+            (0xbf800000, 0x34000000),
+            (0x3fffffff, 0x34000000),
+            (0x00800000, 0x00000001),
+            (0x00000000, 0x00000001),
+            (0x00000123, 0x00000001),
+            (0x0b000000, 0x00200000),
+            (0x0c000000, 0x00800000),
+            (0x7f7fffff, 0x73800000),
+            (0x7f800000, 0x7f800000),
+            (0xff800000, 0x7f800000),
+        ];
+        for (x, ulp) in values.iter() {
+            assert_eq!(F32::from_bits(*x).ulp().to_bits(), *ulp, "{:x}", x);
+        }
+        assert_eq!(BF16::from_bits(0x3f80).ulp().to_bits(), 0x3c00);
+        assert_eq!(F64::from_bits(0x0010000000000000).ulp().to_bits(), 0x1);
+        assert!(F64::quiet_nan().ulp().is_nan());
+        let a = F128::from_bits(0x3fff << 112);
+        assert_eq!(a.ulp().to_bits(), (0x3fff - 112) << 112);
+    }
+
+    #[test]
+    fn ulp_distance() {
+        let a = F16::from_bits(0x3c00);
+        assert_eq!(a.ulp_distance(F16::from_bits(0x3c05)), 5);
+        assert_eq!(F16::from_bits(0x3c05).ulp_distance(a), 5);
+        assert_eq!(
+            F16::from_bits(0x0000).ulp_distance(F16::from_bits(0x8000)),
+            0
+        );
+        assert_eq!(
+            F16::from_bits(0x0002).ulp_distance(F16::from_bits(0x8003)),
+            5
+        );
+        assert_eq!(
+            F16::from_bits(0x8002).ulp_distance(F16::from_bits(0x0000)),
+            2
+        );
+        assert_eq!(
+            F16::from_bits(0x7bff).ulp_distance(F16::from_bits(0x7c00)),
+            1
+        );
+        assert_eq!(a.ulp_distance(F16::quiet_nan()), u16::MAX);
+        let b = F128::from_bits(0x7fff << 112);
+        assert_eq!(b.ulp_distance(b.neg()), 0xfffe << 112);
+    }
+
+    #[test]
+    fn scale_b() {
+        let rnd = RoundingMode::TiesToEven;
+        let a = F32::from_bits(0x3fc00000);
+        assert_eq!(a.scale_b(3, rnd).to_bits(), 0x41400000);
+        assert_eq!(a.scale_b(-3, rnd).to_bits(), 0x3e400000);
+        assert_eq!(a.ldexp(-3, rnd).to_bits(), 0x3e400000);
+        assert_eq!(a.scale_b(-149, rnd).to_bits(), 0x00000002);
+        assert_eq!(
+            a.scale_b(-149, RoundingMode::TowardZero).to_bits(),
+            0x00000001
+        );
+        let (d, flag) = ExceptionFlags::capture(|| a.scale_b(-150, rnd));
+        assert_eq!(d.to_bits(), 0x00000001);
+        assert!(flag.is_underflow() && flag.is_inexact());
+        let (d, flag) = ExceptionFlags::capture(|| a.scale_b(i32::MAX, rnd));
+        assert!(d.is_positive_infinity());
+        assert!(flag.is_overflow());
+        let d = a.neg().scale_b(200, RoundingMode::TowardPositive);
+        assert_eq!(d.to_bits(), 0xff7fffff);
+        let d = F32::from_bits(0x00000001).scale_b(149, rnd);
+        assert_eq!(d.to_bits(), 0x3f800000);
+        let (d, flag) =
+            ExceptionFlags::capture(|| F32::from_bits(0x00000001).scale_b(i32::MIN, rnd));
+        assert_eq!(d.to_bits(), 0x00000000);
+        assert!(flag.is_underflow());
+        assert_eq!(F32::negative_zero().scale_b(10, rnd).to_bits(), 0x80000000);
+        assert!(F32::negative_infinity()
+            .scale_b(-10, rnd)
+            .is_negative_infinity());
+        assert!(F32::quiet_nan().scale_b(1, rnd).is_nan());
+    }
+
+    #[test]
+    fn log_b() {
+        assert_eq!(F32::from_bits(0x3f800000).log_b(), 0);
+        assert_eq!(F32::from_bits(0x41400000).log_b(), 3);
+        assert_eq!(F32::from_bits(0xbe400000).log_b(), -3);
+        assert_eq!(F32::from_bits(0x00800000).log_b(), -126);
+        assert_eq!(F32::from_bits(0x00400000).log_b(), -127);
+        assert_eq!(F32::from_bits(0x00000001).log_b(), -149);
+        assert_eq!(F128::from_bits(0x1).log_b(), -16494);
+        let (d, flag) = ExceptionFlags::capture(|| F32::positive_zero().log_b());
+        assert_eq!(d, i32::MIN);
+        assert!(flag.is_invalid());
+        assert_eq!(F32::negative_infinity().log_b(), i32::MAX);
+        assert_eq!(F32::quiet_nan().log_b(), i32::MIN);
+    }
+
+    #[test]
+    fn frexp() {
+        let (m, e) = F64::from_bits(12.0f64.to_bits()).frexp();
+        assert_eq!((m.to_bits(), e), (0.75f64.to_bits(), 4));
+        let (m, e) = F64::from_bits((-0.3f64).to_bits()).frexp();
+        assert_eq!((m.to_bits(), e), ((-0.6f64).to_bits(), -1));
+        let (m, e) = F16::from_bits(0x0001).frexp();
+        assert_eq!((m.to_bits(), e), (0x3800, -23));
+        let (m, e) = F16::negative_zero().frexp();
+        assert_eq!((m.to_bits(), e), (0x8000, 0));
+        let (m, e) = BF16::from_bits(0x3f81).frexp();
+        assert_eq!(
+            (m.ldexp(e, RoundingMode::TiesToEven).to_bits(), e),
+            (0x3f81, 1)
+        );
+    }
+
+    #[test]
+    fn decompose() {
+        let rnd = RoundingMode::TiesToEven;
+        let values = [
+            0x3c00, 0xbc01, 0x0001, 0x83ff, 0x0400, 0x7bff, 0x0000, 0x8000,
+        ];
+        for x in values.iter() {
+            let (sign, e, m) = F16::from_bits(*x).decompose().unwrap();
+            assert_eq!(F16::compose(sign, e, m, rnd).to_bits(), *x);
+        }
+        assert_eq!(
+            F16::from_bits(0x3c00).decompose(),
+            Some((false, -10, 0x400))
+        );
+        assert_eq!(F16::from_bits(0x8001).decompose(), Some((true, -24, 0x1)));
+        assert_eq!(F16::from_bits(0x7c00).decompose(), None);
+        assert_eq!(F16::from_bits(0x7e00).decompose(), None);
+        let (sign, e, m) = F128::from_bits(0xc000 << 112).decompose().unwrap();
+        assert_eq!((sign, e, m), (true, -111, 1 << 112));
+
+        let (d, flag) = ExceptionFlags::capture(|| F16::compose(false, -15, 0x7ff7, rnd));
+        assert_eq!(d.to_bits(), 0x3bff);
+        assert!(flag.is_inexact());
+        let d = F16::compose(false, -19, 0x7ffff, rnd);
+        assert_eq!(d.to_bits(), 0x3c00);
+        let d = F32::compose(true, 0, u128::MAX, RoundingMode::TowardZero);
+        assert_eq!(d.to_bits(), 0xff7fffff);
+    }
+
+    #[test]
+    fn class() {
+        let values = [
+            (0x7f800001, FloatClass::SignalingNaN, 0x100, FpCategory::Nan),
+            (0xffc00000, FloatClass::QuietNaN, 0x200, FpCategory::Nan),
+            (
+                0xff800000,
+                FloatClass::NegativeInfinity,
+                0x001,
+                FpCategory::Infinite,
+            ),
+            (
+                0xbf800000,
+                FloatClass::NegativeNormal,
+                0x002,
+                FpCategory::Normal,
+            ),
+            (
+                0x807fffff,
+                FloatClass::NegativeSubnormal,
+                0x004,
+                FpCategory::Subnormal,
+            ),
+            (
+                0x80000000,
+                FloatClass::NegativeZero,
+                0x008,
+                FpCategory::Zero,
+            ),
+            (
+                0x00000000,
+                FloatClass::PositiveZero,
+                0x010,
+                FpCategory::Zero,
+            ),
+            (
+                0x00000001,
+                FloatClass::PositiveSubnormal,
+                0x020,
+                FpCategory::Subnormal,
+            ),
+            (
+                0x00800000,
+                FloatClass::PositiveNormal,
+                0x040,
+                FpCategory::Normal,
+            ),
+            (
+                0x7f800000,
+                FloatClass::PositiveInfinity,
+                0x080,
+                FpCategory::Infinite,
+            ),
+        ];
+        for (x, class, mask, category) in values.iter() {
+            let x = F32::from_bits(*x);
+            assert_eq!(x.class(), *class);
+            assert_eq!(x.fclass_mask(), *mask);
+            assert_eq!(FpCategory::from(x.class()), *category);
+        }
+        let d = F128::from_bits(0x7fff4000 << 96);
+        assert_eq!(d.class(), FloatClass::SignalingNaN);
+        assert_eq!(
+            BF16::from_bits(0x0001).class(),
+            FloatClass::PositiveSubnormal
+        );
+    }
+
+    #[test]
+    fn predicates() {
+        let zero = F16::from_bits(0x0000);
+        let sub = F16::from_bits(0x0001);
+        let norm = F16::from_bits(0x3c00);
+        let inf = F16::from_bits(0xfc00);
+        let qnan = F16::from_bits(0x7e00);
+        let snan = F16::from_bits(0x7d00);
+        assert_eq!(
+            [zero.is_subnormal(), sub.is_subnormal(), norm.is_subnormal()],
+            [false, true, false]
+        );
+        assert_eq!(
+            [
+                zero.is_normal(),
+                sub.is_normal(),
+                norm.is_normal(),
+                inf.is_normal()
+            ],
+            [false, false, true, false]
+        );
+        assert_eq!(
+            [
+                zero.is_finite(),
+                norm.is_finite(),
+                inf.is_finite(),
+                qnan.is_finite()
+            ],
+            [true, true, false, false]
+        );
+        assert_eq!(
+            [norm.is_infinite(), inf.is_infinite(), qnan.is_infinite()],
+            [false, true, false]
+        );
+        assert_eq!(
+            [
+                norm.is_quiet_nan(),
+                qnan.is_quiet_nan(),
+                snan.is_quiet_nan()
+            ],
+            [false, true, false]
+        );
+        assert!(snan.is_canonical());
+    }
+
+    #[test]
+    fn nan_payload() {
+        let rnd = RoundingMode::TiesToEven;
+        assert_eq!(F32::signaling_nan().to_bits(), 0x7fa00000);
+        assert_eq!(F64::signaling_nan().to_bits(), 0x7ff4000000000000);
+        assert!(F16::signaling_nan().is_signaling_nan());
+
+        let d = F32::from_bits(0x7fc01234).get_payload();
+        assert_eq!(d.to_bits(), F32::from_u32(0x1234, rnd).to_bits());
+        let d = F32::from_bits(0xff801234).get_payload();
+        assert_eq!(d.to_bits(), F32::from_u32(0x1234, rnd).to_bits());
+        let d = F32::from_bits(0x3f800000).get_payload();
+        assert_eq!(d.to_bits(), 0xbf800000);
+
+        let x = F32::from_u32(0x1234, rnd);
+        assert_eq!(F32::from_payload(x).to_bits(), 0x7fc01234);
+        assert_eq!(F32::from_payload_signaling(x).to_bits(), 0x7f801234);
+        assert_eq!(
+            F32::from_payload(F32::positive_zero()).to_bits(),
+            0x7fc00000
+        );
+        assert_eq!(
+            F32::from_payload_signaling(F32::positive_zero()).to_bits(),
+            0
+        );
+        let max = F32::from_u32(0x3fffff, rnd);
+        assert_eq!(F32::from_payload(max).to_bits(), 0x7fffffff);
+        let d = F128::from_u64(u64::MAX, rnd);
+        assert_eq!(
+            F128::from_payload(d).to_bits(),
+            0x7fff8000 << 96 | u64::MAX as u128
+        );
+
+        let invalid = [0x4a800000, 0xbf800000, 0x3fc00000, 0x7f800000, 0x7fc00000];
+        for x in invalid.iter() {
+            assert_eq!(F32::from_payload(F32::from_bits(*x)).to_bits(), 0);
+        }
+        let x = F32::from_bits(0x7fc05678);
+        assert_eq!(F32::from_payload(x.get_payload()).to_bits(), 0x7fc05678);
+    }
+
+    #[test]
+    fn canonicalize() {
+        let a = F32::from_bits(0x7f801234);
+        let (d, flag) = ExceptionFlags::capture(|| Specialization::X86.scope(|| a.canonicalize()));
+        assert_eq!(d.to_bits(), 0xffc00000);
+        assert!(flag.is_invalid());
+        let d = Specialization::RiscV.scope(|| F32::from_bits(0xffc01234).canonicalize());
+        assert_eq!(d.to_bits(), 0x7fc00000);
+        let d =
+            Specialization::ArmVfpv2.scope(|| F64::from_bits(0xfff8000000001234).canonicalize());
+        assert_eq!(d.to_bits(), 0x7ff8000000000000);
+        let (d, flag) = ExceptionFlags::capture(|| F32::from_bits(0xbf800000).canonicalize());
+        assert_eq!(d.to_bits(), 0xbf800000);
+        assert!(!flag.is_invalid());
+    }
+
+    #[test]
+    fn compare_quiet() {
+        let a = F32::from_bits(0x3f800000);
+        let b = F32::from_bits(0x40000000);
+        let qnan = F32::quiet_nan();
+        let snan = F32::signaling_nan();
+        assert_eq!(a.compare_quiet(b), Some(Ordering::Less));
+        assert_eq!(b.compare_quiet(a), Some(Ordering::Greater));
+        assert_eq!(
+            a.compare_quiet(F32::from_bits(0x3f800000)),
+            Some(Ordering::Equal)
+        );
+        assert_eq!(
+            F32::from_bits(0x80000000).compare_quiet(F32::from_bits(0)),
+            Some(Ordering::Equal)
+        );
+
+        let (d, flag) = ExceptionFlags::capture(|| a.compare_quiet(qnan));
+        assert_eq!(d, None);
+        assert!(!flag.is_invalid());
+        let (d, flag) = ExceptionFlags::capture(|| snan.compare_quiet(a));
+        assert_eq!(d, None);
+        assert!(flag.is_invalid());
+        let (_, flag) = ExceptionFlags::capture(|| a.compare(qnan));
+        assert!(flag.is_invalid());
+    }
+
+    #[test]
+    fn compare_predicate() {
+        use Predicate::*;
+        let a = F64::from_bits(0x3ff0000000000000);
+        let b = F64::from_bits(0x4000000000000000);
+        let qnan = F64::quiet_nan();
+        // (predicate, a < b, a == a, b > a, a ? NaN)
+        let expected = [
+            (QuietEqual, false, true, false, false),
+            (QuietNotEqual, true, false, true, true),
+            (QuietGreater, false, false, true, false),
+            (QuietGreaterEqual, false, true, true, false),
+            (QuietLess, true, false, false, false),
+            (QuietLessEqual, true, true, false, false),
+            (QuietUnordered, false, false, false, true),
+            (QuietNotGreater, true, true, false, true),
+            (QuietLessUnordered, true, false, false, true),
+            (QuietNotLess, false, true, true, true),
+            (QuietGreaterUnordered, false, false, true, true),
+            (QuietOrdered, true, true, true, false),
+            (SignalingEqual, false, true, false, false),
+            (SignalingNotEqual, true, false, true, true),
+            (SignalingGreater, false, false, true, false),
+            (SignalingGreaterEqual, false, true, true, false),
+            (SignalingLess, true, false, false, false),
+            (SignalingLessEqual, true, true, false, false),
+            (SignalingNotGreater, true, true, false, true),
+            (SignalingLessUnordered, true, false, false, true),
+            (SignalingNotLess, false, true, true, true),
+            (SignalingGreaterUnordered, false, false, true, true),
+        ];
+        for (p, lt, eq, gt, nan) in expected.iter() {
+            let (d, flag) = ExceptionFlags::capture(|| {
+                (
+                    a.compare_predicate(b, *p),
+                    a.compare_predicate(a, *p),
+                    b.compare_predicate(a, *p),
+                )
+            });
+            assert_eq!(d, (*lt, *eq, *gt));
+            assert!(!flag.is_invalid());
+            let (d, flag) = ExceptionFlags::capture(|| a.compare_predicate(qnan, *p));
+            assert_eq!(d, *nan);
+            assert_eq!(
+                flag.is_invalid(),
+                format!("{:?}", p).starts_with("Signaling")
+            );
+            let (_, flag) =
+                ExceptionFlags::capture(|| F64::signaling_nan().compare_predicate(a, *p));
+            assert!(flag.is_invalid());
+        }
+    }
+
+    #[test]
+    fn round_to_integral_exact() {
+        let a = F64::from_bits(0x3ff8000000000000);
+        let (d, flag) = a.round_to_integral_with_flags(RoundingMode::TiesToEven);
+        assert_eq!(d.to_bits(), 0x4000000000000000);
+        assert!(!flag.is_inexact());
+        let (d, flag) = a.round_to_integral_exact_with_flags(RoundingMode::TiesToEven);
+        assert_eq!(d.to_bits(), 0x4000000000000000);
+        assert!(flag.is_inexact());
+        let (d, flag) = F64::from_bits(0x4000000000000000)
+            .round_to_integral_exact_with_flags(RoundingMode::TowardZero);
+        assert_eq!(d.to_bits(), 0x4000000000000000);
+        assert!(!flag.is_inexact());
+        let (d, flag) =
+            BF16::from_bits(0x3fc0).round_to_integral_exact_with_flags(RoundingMode::TowardZero);
+        assert_eq!(d.to_bits(), 0x3f80);
+        assert!(flag.is_inexact());
+        let (d, flag) = F128::from_bits(0x3fff8000000000000000000000000000)
+            .round_to_integral_exact_with_flags(RoundingMode::TiesToAway);
+        assert_eq!(d.to_bits(), 0x40000000000000000000000000000000);
+        assert!(flag.is_inexact());
+
+        // the default implementation is the same as SoftFloat
+        for rnd in [
+            RoundingMode::TiesToEven,
+            RoundingMode::TowardNegative,
+            RoundingMode::ToOdd,
+        ] {
+            for x in (0..=0xffff).step_by(7) {
+                let a = F16::from_bits(x);
+                let d = ExceptionFlags::capture(|| a.round_to_integral_exact(rnd));
+                let e = ExceptionFlags::capture(|| op::round_to_integral_exact(&a, rnd));
+                assert_eq!((d.0.to_bits(), d.1), (e.0.to_bits(), e.1));
+            }
+        }
+    }
+
+    #[test]
+    fn round_to_integral_dynamic() {
+        let a = F16::from_bits(0xbe00);
+        let (d, flag) = ExceptionFlags::capture(|| {
+            RoundingMode::TowardNegative.set();
+            assert_eq!(RoundingMode::current(), RoundingMode::TowardNegative);
+            let d = a.round_to_integral_dynamic(false);
+            RoundingMode::TowardZero.set();
+            (d, a.round_to_integral_dynamic(true))
+        });
+        assert_eq!(d.0.to_bits(), 0xc000);
+        assert_eq!(d.1.to_bits(), 0xbc00);
+        assert!(flag.is_inexact());
+    }
+
+    #[test]
+    fn to_int() {
+        let rnd = RoundingMode::TiesToEven;
+        let a = F32::from_bits(0x42fe0000); // 127
+        assert_eq!(a.to_int::<i8>(rnd, false), 127);
+        assert_eq!(a.to_int::<u8>(rnd, false), 127);
+        assert_eq!(a.to_int::<i64>(rnd, false), 127);
+
+        // 127.5
+        let a = F32::from_bits(0x42ff0000);
+        let (d, flag) = a.to_int_with_flags::<u8>(rnd, true);
+        assert_eq!(d, 128);
+        assert!(flag.is_inexact() && !flag.is_invalid());
+        let (d, flag) = a.to_int_with_flags::<u8>(RoundingMode::TowardZero, false);
+        assert_eq!(d, 127);
+        assert_eq!(flag.to_bits(), 0);
+        let (_, flag) = a.to_int_with_flags::<i8>(rnd, true);
+        assert!(flag.is_invalid() && !flag.is_inexact());
+
+        let a = F64::from_bits(0xc0e0000000000000); // -32768
+        let (d, flag) = a.to_int_with_flags::<i16>(rnd, true);
+        assert_eq!(d, i16::MIN);
+        assert_eq!(flag.to_bits(), 0);
+        let (_, flag) = a.to_int_with_flags::<u16>(rnd, true);
+        assert!(flag.is_invalid());
+        let (d, flag) = F16::from_bits(0xb800).to_int_with_flags::<u8>(rnd, true); // -0.5
+        assert_eq!(d, 0);
+        assert!(flag.is_inexact() && !flag.is_invalid());
+
+        let nan = F32::quiet_nan();
+        let (_, flag) = nan.to_int_with_flags::<u16>(rnd, false);
+        assert!(flag.is_invalid());
+        let d = Specialization::X86Sse
+            .scope(|| (nan.to_int::<u8>(rnd, false), nan.to_int::<i8>(rnd, false)));
+        assert_eq!(d, (u8::MAX, i8::MIN));
+        let d = Specialization::ArmVfpv2.scope(|| {
+            (
+                nan.to_int::<i16>(rnd, false),
+                F32::from_bits(0xc7000000).to_int::<i8>(rnd, false),
+            )
+        });
+        assert_eq!(d, (0, i8::MIN));
+    }
+
+    #[test]
+    fn int128() {
+        let rnd = RoundingMode::TiesToEven;
+        let (d, flag) = F128::from_u128_with_flags(u128::MAX, rnd);
+        assert_eq!(d.to_bits(), 0x407f0000000000000000000000000000);
+        assert!(flag.is_inexact());
+        let (d, flag) = F128::from_u128_with_flags(u128::MAX, RoundingMode::TowardZero);
+        assert_eq!(d.to_bits(), 0x407effffffffffffffffffffffffffff);
+        assert!(flag.is_inexact());
+        let (d, flag) = F128::from_i128_with_flags(-(1 << 112) - 1, rnd);
+        assert_eq!(d.to_bits(), 0xc06f0000000000000000000000000001);
+        assert_eq!(flag.to_bits(), 0);
+        assert_eq!(F32::from_i128(i128::MIN, rnd).to_bits(), 0xff000000);
+        assert_eq!(F64::from_u128(0, rnd).to_bits(), 0);
+        let (d, flag) = F16::from_u128_with_flags(1 << 16, rnd);
+        assert_eq!(d.to_bits(), 0x7c00);
+        assert!(flag.is_overflow() && flag.is_inexact());
+
+        let a = F128::from_bits(0x407e0000000000000000000000000000); // 2^127
+        assert_eq!(a.to_u128(rnd, false), 1 << 127);
+        let (d, flag) = a.to_i128_with_flags(rnd, false);
+        assert_eq!(d, i128::MIN);
+        assert!(flag.is_invalid());
+        let (d, flag) = a.neg().to_i128_with_flags(rnd, false);
+        assert_eq!(d, i128::MIN);
+        assert_eq!(flag.to_bits(), 0);
+        let (_, flag) = F32::from_bits(0x7f800000).to_u128_with_flags(rnd, false);
+        assert!(flag.is_invalid());
+        assert_eq!(
+            F32::from_bits(0x7f7fffff).to_u128(rnd, false),
+            0xffffff << 104
+        );
+        let (_, flag) = F64::from_bits(0x47f0000000000000).to_u128_with_flags(rnd, false);
+        assert!(flag.is_invalid());
+        assert_eq!(F32::from_bits(0x7f000000).to_u128(rnd, false), 1 << 127);
+
+        // -2.5
+        let a = F64::from_bits(0xc004000000000000);
+        let (d, flag) = a.to_i128_with_flags(rnd, true);
+        assert_eq!(d, -2);
+        assert!(flag.is_inexact());
+        let (d, flag) = a.to_i128_with_flags(RoundingMode::TowardNegative, false);
+        assert_eq!(d, -3);
+        assert_eq!(flag.to_bits(), 0);
+        assert_eq!(a.to_int::<i128>(RoundingMode::TiesToAway, false), -3);
+        let (_, flag) = a.to_u128_with_flags(rnd, true);
+        assert!(flag.is_invalid() && !flag.is_inexact());
+        let (d, flag) = F64::from_bits(0xbfd0000000000000).to_u128_with_flags(rnd, true);
+        assert_eq!(d, 0);
+        assert!(flag.is_inexact() && !flag.is_invalid());
+
+        let nan = F64::quiet_nan();
+        let d = Specialization::RiscV.scope(|| (nan.to_u128(rnd, false), nan.to_i128(rnd, false)));
+        assert_eq!(d, (u128::MAX, i128::MAX));
+    }
+
+    #[test]
+    fn round_to_odd() {
+        let rnd = RoundingMode::ToOdd;
+        // 1 + 2^-53
+        let a = F128::from_bits(0x3fff0000000000000000000000000000 | 1 << 59);
+        let (d, flag) = a.to_f64_with_flags(rnd);
+        assert_eq!(d.to_bits(), 0x3ff0000000000001);
+        assert!(flag.is_inexact());
+        assert_eq!(
+            a.to_f64(RoundingMode::TiesToEven).to_bits(),
+            0x3ff0000000000000
+        );
+
+        let a = F32::from_bits(0x3f800000);
+        assert_eq!(a.add(F32::from_bits(0x30800000), rnd).to_bits(), 0x3f800001);
+        assert_eq!(a.add(a, rnd).to_bits(), 0x40000000);
+        let a = BF16::from_bits(0x3f80);
+        assert_eq!(a.add(BF16::from_bits(0x3380), rnd).to_bits(), 0x3f81);
+        assert_eq!(a.add(a, rnd).to_bits(), 0x4000);
+
+        let a = F64::from_bits(0x7fefffffffffffff);
+        let (d, flag) = a.add_with_flags(a, rnd);
+        assert_eq!(d.to_bits(), 0x7fefffffffffffff);
+        assert!(flag.is_overflow() && flag.is_inexact());
+        assert_eq!(F16::from_u128(2049, rnd).to_bits(), 0x6801);
+        assert_eq!(F16::from_u128(0x8000_0000, rnd).to_bits(), 0x7bff);
+        assert_eq!(
+            F16::from_str_rounded("1e-10", rnd).unwrap().0.to_bits(),
+            0x0001
+        );
+
+        // 2.5, 4.5 and 3
+        for (x, e) in [(0x4020, 3), (0x4090, 5), (0x4040, 3)].iter() {
+            let a = BF16::from_bits(*x);
+            assert_eq!(a.to_i32(rnd, false), *e);
+            assert_eq!(a.to_i128(rnd, false), *e as i128);
+            assert_eq!(a.round_to_integral(rnd).to_i32(rnd, true), *e);
+        }
+    }
+
+    #[test]
+    fn tininess_scope_panic() {
+        let tininess = Tininess::current();
+        let other = match tininess {
+            Tininess::AfterRounding => Tininess::BeforeRounding,
+            Tininess::BeforeRounding => Tininess::AfterRounding,
+        };
+        let ret = std::panic::catch_unwind(|| other.scope(|| panic!("in scope")));
+        assert!(ret.is_err());
+        assert_eq!(Tininess::current(), tininess);
+    }
+
+    #[test]
+    fn tininess() {
+        let rnd = RoundingMode::TiesToEven;
+        let before = Tininess::BeforeRounding;
+        let after = Tininess::AfterRounding;
+        Specialization::X86Sse.scope(|| {
+            assert_eq!(Tininess::current(), after);
+            before.scope(|| assert_eq!(Tininess::current(), before));
+            assert_eq!(Tininess::current(), after);
+        });
+
+        // (1 - 2^-12) * 2^-14 rounds up to the minimum normal number
+        let a = F16::from_bits(0x03ff);
+        let b = F16::from_bits(0x3c01);
+        for (t, underflow) in [(before, true), (after, false)].iter() {
+            let (d, flag) = t.scope(|| a.mul_with_flags(b, rnd));
+            assert_eq!(d.to_bits(), 0x0400);
+            assert_eq!(flag.is_underflow(), *underflow);
+            let (d, flag) =
+                t.scope(|| BF16::from_bits(0x007f).mul_with_flags(BF16::from_bits(0x3f81), rnd));
+            assert_eq!(d.to_bits(), 0x0080);
+            assert_eq!(flag.is_underflow(), *underflow);
+            let (d, flag) = t.scope(|| F16::from_str_rounded("6.1032e-5", rnd).unwrap());
+            assert_eq!(d.to_bits(), 0x0400);
+            assert_eq!(flag.is_underflow(), *underflow);
+        }
+    }
+
+    #[test]
+    fn exception_flags_capture_panic() {
+        let a = F32::from_bits(0x3f800000);
+        let b = F32::from_bits(0x40400000);
+        ExceptionFlags::INVALID.set();
+        RoundingMode::TowardZero.set();
+        let ret = std::panic::catch_unwind(|| {
+            ExceptionFlags::capture(|| {
+                RoundingMode::TowardPositive.set();
+                let _ = a.div(b, RoundingMode::TiesToEven);
+                panic!("in capture");
+            })
+        });
+        assert!(ret.is_err());
+        let mut flag = ExceptionFlags::default();
+        flag.get();
+        assert_eq!(flag, ExceptionFlags::INVALID);
+        assert_eq!(RoundingMode::current(), RoundingMode::TowardZero);
+        RoundingMode::TiesToEven.set();
+        ExceptionFlags::default().set();
+    }
+
+    #[test]
+    fn exception_flags_bitset() {
+        let a = F32::from_bits(0x00000000);
+        let (_, flag) = ExceptionFlags::capture(|| a.div(a, RoundingMode::TiesToEven));
+        assert_eq!(flag, ExceptionFlags::INVALID);
+        let (_, flag) = ExceptionFlags::capture(|| {
+            F32::from_bits(0x7f7fffff).mul(F32::from_bits(0x40000000), RoundingMode::TiesToEven)
+        });
+        assert_eq!(flag, ExceptionFlags::OVERFLOW | ExceptionFlags::INEXACT);
+
+        let mut flag = ExceptionFlags::default();
+        assert!(flag.is_empty());
+        flag.insert(ExceptionFlags::UNDERFLOW | ExceptionFlags::INEXACT);
+        flag |= ExceptionFlags::INFINITE;
+        assert!(flag.contains(ExceptionFlags::UNDERFLOW | ExceptionFlags::INEXACT));
+        assert!(!flag.contains(ExceptionFlags::UNDERFLOW | ExceptionFlags::INVALID));
+        flag.remove(ExceptionFlags::UNDERFLOW);
+        assert!(!flag.is_underflow() && flag.is_inexact());
+        assert_eq!(flag & ExceptionFlags::INEXACT, ExceptionFlags::INEXACT);
+        flag &= !ExceptionFlags::INEXACT;
+        assert_eq!(flag, ExceptionFlags::INFINITE);
+        assert_eq!((!ExceptionFlags::default()).to_bits(), 0x3f);
+        assert_eq!(
+            (!ExceptionFlags::default()).iter().collect::<Vec<_>>(),
+            vec![
+                ExceptionFlags::INVALID,
+                ExceptionFlags::INFINITE,
+                ExceptionFlags::OVERFLOW,
+                ExceptionFlags::UNDERFLOW,
+                ExceptionFlags::INEXACT,
+                ExceptionFlags::DENORMAL,
+            ]
+        );
+
+        let mut set = std::collections::HashSet::new();
+        for x in 0..0x40 {
+            let flag = ExceptionFlags::from_bits(x);
+            assert_eq!(flag.to_string().parse::<ExceptionFlags>(), Ok(flag));
+            assert_eq!(flag.iter().count(), x.count_ones() as usize);
+            set.insert(flag);
+        }
+        assert_eq!(set.len(), 0x40);
+        assert_eq!(ExceptionFlags::default().to_string(), "");
+        assert_eq!(
+            (!ExceptionFlags::default()).to_string(),
+            "NV DZ OF UF NX ID"
+        );
+        assert_eq!(format!("[{:>6}]", ExceptionFlags::OVERFLOW), "[    OF]");
+        assert_eq!(
+            " nx  Uf ".parse(),
+            Ok(ExceptionFlags::INEXACT | ExceptionFlags::UNDERFLOW)
+        );
+        let err = "NV XX".parse::<ExceptionFlags>().unwrap_err();
+        assert_eq!(err.to_string(), "invalid exception flag: XX");
+    }
 }
@@ -0,0 +1,833 @@
+use crate::{op, ExceptionFlags, Float, RoundingMode, BF16, F128, F16, F32, F64};
+use num_traits::{
+    Bounded, FloatConst, FromPrimitive, Num, NumCast, One, ParseFloatError, Signed, ToPrimitive,
+    Zero,
+};
+use std::borrow::Borrow;
+use std::cmp::Ordering;
+use std::num::FpCategory;
+use std::ops::{
+    Add, AddAssign, Div, DivAssign, Mul, MulAssign, Neg, Rem, RemAssign, Sub, SubAssign,
+};
+
+// Operators and num-traits use round to nearest even.
+// Transcendental functions are computed by primitive `f64`, so they are not correctly rounded.
+
+const RND: RoundingMode = RoundingMode::TiesToEven;
+
+// constants rounded to binary128
+const E: u128 = 0x40005bf0a8b1457695355fb8ac404e7a;
+const FRAC_1_PI: u128 = 0x3ffd45f306dc9c882a53f84eafa3ea6a;
+const FRAC_1_SQRT_2: u128 = 0x3ffe6a09e667f3bcc908b2fb1366ea95;
+const FRAC_2_PI: u128 = 0x3ffe45f306dc9c882a53f84eafa3ea6a;
+const FRAC_2_SQRT_PI: u128 = 0x3fff20dd750429b6d11ae3a914fed7fe;
+const FRAC_PI_2: u128 = 0x3fff921fb54442d18469898cc51701b8;
+const FRAC_PI_3: u128 = 0x3fff0c152382d73658465bb32e0f567b;
+const FRAC_PI_4: u128 = 0x3ffe921fb54442d18469898cc51701b8;
+const FRAC_PI_6: u128 = 0x3ffe0c152382d73658465bb32e0f567b;
+const FRAC_PI_8: u128 = 0x3ffd921fb54442d18469898cc51701b8;
+const LN_10: u128 = 0x400026bb1bbb5551582dd4adac5705a6;
+const LN_2: u128 = 0x3ffe62e42fefa39ef35793c7673007e6;
+const LOG10_E: u128 = 0x3ffdbcb7b1526e50e32a6ab7555f5a68;
+const LOG2_E: u128 = 0x3fff71547652b82fe1777d0ffda0d23a;
+const PI: u128 = 0x4000921fb54442d18469898cc51701b8;
+const SQRT_2: u128 = 0x3fff6a09e667f3bcc908b2fb1366ea95;
+
+macro_rules! impl_const {
+    ($t:ident, $to:ident, $($name:ident),*) => {
+        impl FloatConst for $t {
+            $(
+                fn $name() -> Self {
+                    Float::$to(&F128::from_bits($name), RND)
+                }
+            )*
+        }
+    };
+}
+
+macro_rules! impl_op {
+    ($t:ident, $op:ident, $op_fn:ident, $assign:ident, $assign_fn:ident) => {
+        impl $op for $t {
+            type Output = Self;
+
+            fn $op_fn(self, x: Self) -> Self {
+                Float::$op_fn(&self, x, RND)
+            }
+        }
+
+        impl $assign for $t {
+            fn $assign_fn(&mut self, x: Self) {
+                *self = Float::$op_fn(self, x, RND);
+            }
+        }
+    };
+}
+
+macro_rules! impl_num {
+    ($t:ident, $to:ident) => {
+        impl $t {
+            fn from_std(x: f64) -> Self {
+                Float::$to(&F64::from_bits(x.to_bits()), RND)
+            }
+
+            fn to_std(self) -> f64 {
+                f64::from_bits(Float::to_f64(&self, RND).to_bits())
+            }
+
+            fn via_std<O: FnOnce(f64) -> f64>(self, op: O) -> Self {
+                Self::from_std(op(self.to_std()))
+            }
+
+            /// Same as `Float::eq`, which is ambiguous with `PartialEq::eq` otherwise
+            #[inline]
+            #[allow(clippy::should_implement_trait)]
+            pub fn eq<T: Borrow<Self>>(&self, x: T) -> bool {
+                Float::eq(self, x)
+            }
+
+            /// Same as `Float::lt`, which is ambiguous with `PartialOrd::lt` otherwise
+            #[inline]
+            pub fn lt<T: Borrow<Self>>(&self, x: T) -> bool {
+                Float::lt(self, x)
+            }
+
+            /// Same as `Float::le`, which is ambiguous with `PartialOrd::le` otherwise
+            #[inline]
+            pub fn le<T: Borrow<Self>>(&self, x: T) -> bool {
+                Float::le(self, x)
+            }
+        }
+
+        impl_op!($t, Add, add, AddAssign, add_assign);
+        impl_op!($t, Sub, sub, SubAssign, sub_assign);
+        impl_op!($t, Mul, mul, MulAssign, mul_assign);
+        impl_op!($t, Div, div, DivAssign, div_assign);
+
+        /// `%` truncates the quotient like primitive floats, so it differs from `Float::rem`
+        impl Rem for $t {
+            type Output = Self;
+
+            fn rem(self, x: Self) -> Self {
+                op::fmod(&self, &x)
+            }
+        }
+
+        impl RemAssign for $t {
+            fn rem_assign(&mut self, x: Self) {
+                *self = *self % x;
+            }
+        }
+
+        impl Neg for $t {
+            type Output = Self;
+
+            fn neg(self) -> Self {
+                Float::neg(&self)
+            }
+        }
+
+        impl PartialEq for $t {
+            fn eq(&self, x: &Self) -> bool {
+                Float::eq(self, x)
+            }
+        }
+
+        impl PartialOrd for $t {
+            fn partial_cmp(&self, x: &Self) -> Option<Ordering> {
+                if Float::is_nan(self) || Float::is_nan(x) {
+                    None
+                } else if Float::eq(self, x) {
+                    Some(Ordering::Equal)
+                } else if Float::lt_quiet(self, x) {
+                    Some(Ordering::Less)
+                } else {
+                    Some(Ordering::Greater)
+                }
+            }
+
+            fn lt(&self, x: &Self) -> bool {
+                Float::lt_quiet(self, x)
+            }
+
+            fn le(&self, x: &Self) -> bool {
+                Float::le_quiet(self, x)
+            }
+
+            fn gt(&self, x: &Self) -> bool {
+                Float::lt_quiet(x, self)
+            }
+
+            fn ge(&self, x: &Self) -> bool {
+                Float::le_quiet(x, self)
+            }
+        }
+
+        impl Zero for $t {
+            fn zero() -> Self {
+                Self::positive_zero()
+            }
+
+            fn is_zero(&self) -> bool {
+                Float::is_zero(self)
+            }
+        }
+
+        impl One for $t {
+            fn one() -> Self {
+                Float::from_u8(1, RND)
+            }
+        }
+
+        /// `from_str_radix` parses radix 10 with correct rounding, but parses other radixes by primitive `f64`,
+        /// so the precision and range of the result are limited to `f64`, and narrower types are rounded twice.
+        impl Num for $t {
+            type FromStrRadixErr = ParseFloatError;
+
+            fn from_str_radix(s: &str, radix: u32) -> Result<Self, Self::FromStrRadixErr> {
+                if radix == 10 {
+                    s.parse()
+                } else {
+                    <f64 as Num>::from_str_radix(s, radix).map(Self::from_std)
+                }
+            }
+        }
+
+        impl Bounded for $t {
+            fn min_value() -> Self {
+                Float::neg(&<Self as Bounded>::max_value())
+            }
+
+            fn max_value() -> Self {
+                Self::from_bits(
+                    ((Self::EXPONENT_BIT - 1) << Self::EXPONENT_POS) | Self::FRACTION_BIT,
+                )
+            }
+        }
+
+        impl Signed for $t {
+            fn abs(&self) -> Self {
+                Float::abs(self)
+            }
+
+            fn abs_sub(&self, x: &Self) -> Self {
+                <Self as num_traits::Float>::abs_sub(*self, *x)
+            }
+
+            fn signum(&self) -> Self {
+                <Self as num_traits::Float>::signum(*self)
+            }
+
+            fn is_positive(&self) -> bool {
+                Float::is_positive(self)
+            }
+
+            fn is_negative(&self) -> bool {
+                Float::is_negative(self)
+            }
+        }
+
+        impl ToPrimitive for $t {
+            fn to_i64(&self) -> Option<i64> {
+                let (ret, flag) = ExceptionFlags::capture(|| {
+                    Float::to_i64(self, RoundingMode::TowardZero, false)
+                });
+                if flag.is_invalid() {
+                    None
+                } else {
+                    Some(ret)
+                }
+            }
+
+            fn to_u64(&self) -> Option<u64> {
+                let (ret, flag) = ExceptionFlags::capture(|| {
+                    Float::to_u64(self, RoundingMode::TowardZero, false)
+                });
+                if flag.is_invalid() {
+                    None
+                } else {
+                    Some(ret)
+                }
+            }
+
+            fn to_i128(&self) -> Option<i128> {
+                let (ret, flag) = ExceptionFlags::capture(|| {
+                    Float::to_i128(self, RoundingMode::TowardZero, false)
+                });
+                if flag.is_invalid() {
+                    None
+                } else {
+                    Some(ret)
+                }
+            }
+
+            fn to_u128(&self) -> Option<u128> {
+                let (ret, flag) = ExceptionFlags::capture(|| {
+                    Float::to_u128(self, RoundingMode::TowardZero, false)
+                });
+                if flag.is_invalid() {
+                    None
+                } else {
+                    Some(ret)
+                }
+            }
+
+            fn to_f32(&self) -> Option<f32> {
+                Some(f32::from_bits(Float::to_f32(self, RND).to_bits()))
+            }
+
+            fn to_f64(&self) -> Option<f64> {
+                Some(self.to_std())
+            }
+        }
+
+        impl FromPrimitive for $t {
+            fn from_i64(x: i64) -> Option<Self> {
+                Some(Float::from_i64(x, RND))
+            }
+
+            fn from_u64(x: u64) -> Option<Self> {
+                Some(Float::from_u64(x, RND))
+            }
+
+            fn from_i128(x: i128) -> Option<Self> {
+                Some(Float::from_i128(x, RND))
+            }
+
+            fn from_u128(x: u128) -> Option<Self> {
+                Some(Float::from_u128(x, RND))
+            }
+
+            fn from_f32(x: f32) -> Option<Self> {
+                Some(Float::$to(&F32::from_bits(x.to_bits()), RND))
+            }
+
+            fn from_f64(x: f64) -> Option<Self> {
+                Some(Self::from_std(x))
+            }
+        }
+
+        /// Integral values are converted exactly, and the others are converted through `f64`
+        impl NumCast for $t {
+            fn from<T: ToPrimitive>(x: T) -> Option<Self> {
+                let f = x.to_f64()?;
+                // a value is integral if it is the same as its truncation, including the sign of zero
+                let integral = |i: f64| i.to_bits() == f.to_bits();
+                if let Some(i) = x.to_i128().filter(|i| integral(*i as f64)) {
+                    Some(Float::from_i128(i, RND))
+                } else if let Some(i) = x.to_u128().filter(|i| integral(*i as f64)) {
+                    Some(Float::from_u128(i, RND))
+                } else {
+                    Some(Self::from_std(f))
+                }
+            }
+        }
+
+        impl_const!(
+            $t,
+            $to,
+            E,
+            FRAC_1_PI,
+            FRAC_1_SQRT_2,
+            FRAC_2_PI,
+            FRAC_2_SQRT_PI,
+            FRAC_PI_2,
+            FRAC_PI_3,
+            FRAC_PI_4,
+            FRAC_PI_6,
+            FRAC_PI_8,
+            LN_10,
+            LN_2,
+            LOG10_E,
+            LOG2_E,
+            PI,
+            SQRT_2
+        );
+
+        /// Transcendental functions like `exp`, `ln`, `powf`, `sin` and `hypot` are computed by primitive `f64`,
+        /// so the precision and range of their results are limited to `f64` even for `F128`,
+        /// and narrower types are rounded twice.
+        impl num_traits::Float for $t {
+            fn nan() -> Self {
+                Self::quiet_nan()
+            }
+
+            fn infinity() -> Self {
+                Self::positive_infinity()
+            }
+
+            fn neg_infinity() -> Self {
+                Self::negative_infinity()
+            }
+
+            fn neg_zero() -> Self {
+                Self::negative_zero()
+            }
+
+            fn min_value() -> Self {
+                <Self as Bounded>::min_value()
+            }
+
+            fn min_positive_value() -> Self {
+                Self::from_bits(1 << Self::EXPONENT_POS)
+            }
+
+            fn epsilon() -> Self {
+                let exp =
+                    (Self::EXPONENT_BIT >> 1) - Self::EXPONENT_POS as <Self as Float>::Payload;
+                Self::from_bits(exp << Self::EXPONENT_POS)
+            }
+
+            fn max_value() -> Self {
+                <Self as Bounded>::max_value()
+            }
+
+            fn is_nan(self) -> bool {
+                Float::is_nan(&self)
+            }
+
+            fn is_infinite(self) -> bool {
+                Float::is_infinite(&self)
+            }
+
+            fn is_finite(self) -> bool {
+                Float::is_finite(&self)
+            }
+
+            fn is_normal(self) -> bool {
+                Float::is_normal(&self)
+            }
+
+            fn classify(self) -> FpCategory {
+                Float::class(&self).into()
+            }
+
+            fn floor(self) -> Self {
+                self.round_to_integral(RoundingMode::TowardNegative)
+            }
+
+            fn ceil(self) -> Self {
+                self.round_to_integral(RoundingMode::TowardPositive)
+            }
+
+            fn round(self) -> Self {
+                self.round_to_integral(RoundingMode::TiesToAway)
+            }
+
+            fn trunc(self) -> Self {
+                self.round_to_integral(RoundingMode::TowardZero)
+            }
+
+            fn fract(self) -> Self {
+                self - self.round_to_integral(RoundingMode::TowardZero)
+            }
+
+            fn abs(self) -> Self {
+                Float::abs(&self)
+            }
+
+            fn signum(self) -> Self {
+                if Float::is_nan(&self) {
+                    self
+                } else if Float::is_negative(&self) {
+                    -Self::one()
+                } else {
+                    Self::one()
+                }
+            }
+
+            fn is_sign_positive(self) -> bool {
+                Float::is_positive(&self)
+            }
+
+            fn is_sign_negative(self) -> bool {
+                Float::is_negative(&self)
+            }
+
+            fn mul_add(self, a: Self, b: Self) -> Self {
+                self.fused_mul_add(a, b, RND)
+            }
+
+            fn recip(self) -> Self {
+                Self::one() / self
+            }
+
+            fn powi(self, n: i32) -> Self {
+                let mut base = self;
+                let mut exp = n.unsigned_abs();
+                let mut ret = Self::one();
+                while exp != 0 {
+                    if exp & 1 != 0 {
+                        ret *= base;
+                    }
+                    exp >>= 1;
+                    // squaring after the last bit may raise overflow for exact results
+                    if exp != 0 {
+                        base *= base;
+                    }
+                }
+                if n < 0 {
+                    ret.recip()
+                } else {
+                    ret
+                }
+            }
+
+            fn powf(self, n: Self) -> Self {
+                let n = n.to_std();
+                self.via_std(|x| x.powf(n))
+            }
+
+            fn sqrt(self) -> Self {
+                Float::sqrt(&self, RND)
+            }
+
+            fn exp(self) -> Self {
+                self.via_std(f64::exp)
+            }
+
+            fn exp2(self) -> Self {
+                self.via_std(f64::exp2)
+            }
+
+            fn ln(self) -> Self {
+                self.via_std(f64::ln)
+            }
+
+            fn log(self, base: Self) -> Self {
+                let base = base.to_std();
+                self.via_std(|x| x.log(base))
+            }
+
+            fn log2(self) -> Self {
+                self.via_std(f64::log2)
+            }
+
+            fn log10(self) -> Self {
+                self.via_std(f64::log10)
+            }
+
+            fn max(self, x: Self) -> Self {
+                if Float::is_nan(&self) || self < x {
+                    x
+                } else {
+                    self
+                }
+            }
+
+            fn min(self, x: Self) -> Self {
+                if Float::is_nan(&self) || x < self {
+                    x
+                } else {
+                    self
+                }
+            }
+
+            fn abs_sub(self, x: Self) -> Self {
+                if self <= x {
+                    Self::zero()
+                } else {
+                    self - x
+                }
+            }
+
+            fn cbrt(self) -> Self {
+                self.via_std(f64::cbrt)
+            }
+
+            fn hypot(self, x: Self) -> Self {
+                let x = x.to_std();
+                self.via_std(|y| y.hypot(x))
+            }
+
+            fn sin(self) -> Self {
+                self.via_std(f64::sin)
+            }
+
+            fn cos(self) -> Self {
+                self.via_std(f64::cos)
+            }
+
+            fn tan(self) -> Self {
+                self.via_std(f64::tan)
+            }
+
+            fn asin(self) -> Self {
+                self.via_std(f64::asin)
+            }
+
+            fn acos(self) -> Self {
+                self.via_std(f64::acos)
+            }
+
+            fn atan(self) -> Self {
+                self.via_std(f64::atan)
+            }
+
+            fn atan2(self, x: Self) -> Self {
+                let x = x.to_std();
+                self.via_std(|y| y.atan2(x))
+            }
+
+            fn sin_cos(self) -> (Self, Self) {
+                (self.sin(), self.cos())
+            }
+
+            fn exp_m1(self) -> Self {
+                self.via_std(f64::exp_m1)
+            }
+
+            fn ln_1p(self) -> Self {
+                self.via_std(f64::ln_1p)
+            }
+
+            fn sinh(self) -> Self {
+                self.via_std(f64::sinh)
+            }
+
+            fn cosh(self) -> Self {
+                self.via_std(f64::cosh)
+            }
+
+            fn tanh(self) -> Self {
+                self.via_std(f64::tanh)
+            }
+
+            fn asinh(self) -> Self {
+                self.via_std(f64::asinh)
+            }
+
+            fn acosh(self) -> Self {
+                self.via_std(f64::acosh)
+            }
+
+            fn atanh(self) -> Self {
+                self.via_std(f64::atanh)
+            }
+
+            /// The mantissa of `F128` is truncated to 64 bits.
+            fn integer_decode(self) -> (u64, i16, i8) {
+                let sign = if Float::is_negative(&self) { -1 } else { 1 };
+                let bias = (Self::EXPONENT_BIT >> 1) as i16;
+                let mut exp = self.exponent() as i16;
+                let mut mantissa = self.fraction() as u128;
+                if exp == 0 {
+                    mantissa <<= 1;
+                } else {
+                    mantissa |= 1 << Self::EXPONENT_POS;
+                }
+                exp -= bias + Self::EXPONENT_POS as i16;
+                let shift = (Self::EXPONENT_POS + 1).saturating_sub(64);
+                ((mantissa >> shift) as u64, exp + shift as i16, sign)
+            }
+        }
+    };
+}
+
+impl_num!(F16, to_f16);
+impl_num!(BF16, to_bf16);
+impl_num!(F32, to_f32);
+impl_num!(F64, to_f64);
+impl_num!(F128, to_f128);
+
+#[cfg(test)]
+mod tests {
+    use super::*;
+    use crate::{Operation, Trap};
+    use num_traits::Float as NumFloat;
+
+    fn norm<T: NumFloat>(x: T, y: T) -> T {
+        (x * x + y * y).sqrt()
+    }
+
+    #[test]
+    fn num_ops() {
+        let a = F32::from_f32(5.5);
+        let b = F32::from_f32(-2.0);
+        assert_eq!((a + b).to_bits(), 3.5f32.to_bits());
+        assert_eq!((a - b).to_bits(), 7.5f32.to_bits());
+        assert_eq!((a * b).to_bits(), (-11.0f32).to_bits());
+        assert_eq!((a / b).to_bits(), (-2.75f32).to_bits());
+        assert_eq!((a % b).to_bits(), 1.5f32.to_bits());
+        assert_eq!((-a % b).to_bits(), (-1.5f32).to_bits());
+        assert_eq!((-a).to_bits(), (-5.5f32).to_bits());
+
+        let mut d = a;
+        d += b;
+        d *= b;
+        d -= a;
+        d /= b;
+        d %= a;
+        let e = (((5.5f32 + -2.0) * -2.0) - 5.5) / -2.0 % 5.5;
+        assert_eq!(d.to_bits(), e.to_bits());
+
+        for x in -8..8 {
+            for y in 1..5 {
+                let d = <F16 as Float>::from_i32(x, RND) % <F16 as Float>::from_i32(y, RND);
+                let e = F16::from_f32(x as f32 % y as f32);
+                assert_eq!(d.to_bits(), e.to_bits());
+            }
+        }
+
+        // flags of `%` are raised by `Rem`
+        let err = Trap::catch(ExceptionFlags::INVALID, || a % F32::zero()).unwrap_err();
+        assert_eq!(err.operation(), Operation::Rem);
+    }
+
+    #[test]
+    fn num_compare() {
+        let a = F16::from_bits(0x3c00);
+        let b = F16::from_bits(0x4000);
+        let nan = F16::quiet_nan();
+        assert!(a < b);
+        assert!(a <= b);
+        assert!(b > a);
+        assert!(b >= a);
+        assert!(a == a);
+        assert!(a != b);
+        assert!(nan != nan);
+        assert_eq!(a.partial_cmp(&nan), None);
+        assert!(F16::from_bits(0x0) == F16::from_bits(0x8000));
+        assert!(a.eq(a));
+        assert!(a.lt(b));
+        assert!(a.le(b));
+    }
+
+    #[test]
+    fn num_generic() {
+        let d = norm(F16::from_f32(3.0), F16::from_f32(4.0));
+        assert_eq!(d.to_bits(), F16::from_f32(5.0).to_bits());
+        let d = norm(BF16::from_f32(3.0), BF16::from_f32(4.0));
+        assert_eq!(d.to_bits(), BF16::from_f32(5.0).to_bits());
+        let d = norm(F128::from_f32(3.0), F128::from_f32(4.0));
+        assert_eq!(d.to_bits(), F128::from_f32(5.0).to_bits());
+    }
+
+    #[test]
+    fn num_float() {
+        let a = F64::from_f64(-2.5);
+        assert_eq!(a.floor().to_bits(), (-3.0f64).to_bits());
+        assert_eq!(a.ceil().to_bits(), (-2.0f64).to_bits());
+        assert_eq!(a.round().to_bits(), (-3.0f64).to_bits());
+        assert_eq!(a.trunc().to_bits(), (-2.0f64).to_bits());
+        assert_eq!(a.fract().to_bits(), (-0.5f64).to_bits());
+        assert_eq!(NumFloat::abs(a).to_bits(), 2.5f64.to_bits());
+        assert_eq!(NumFloat::signum(a).to_bits(), (-1.0f64).to_bits());
+        assert_eq!(a.recip().to_bits(), (-0.4f64).to_bits());
+        assert_eq!(a.powi(3).to_bits(), (-15.625f64).to_bits());
+        assert_eq!(a.powi(-2).to_bits(), 0.16f64.to_bits());
+        assert_eq!(a.exp().to_bits(), (-2.5f64).exp().to_bits());
+        assert_eq!(a.sin().to_bits(), (-2.5f64).sin().to_bits());
+        assert_eq!(
+            a.atan2(F64::from_f64(1.5)).to_bits(),
+            (-2.5f64).atan2(1.5).to_bits()
+        );
+        assert_eq!(NumFloat::max(a, F64::nan()).to_bits(), a.to_bits());
+        assert_eq!(NumFloat::min(F64::nan(), a).to_bits(), a.to_bits());
+        assert_eq!(a.classify(), FpCategory::Normal);
+        assert_eq!(F64::from_bits(1).classify(), FpCategory::Subnormal);
+        assert_eq!(a.integer_decode(), (-2.5f64).integer_decode());
+        assert_eq!(
+            F128::from_f64(-2.5).integer_decode(),
+            (0xa000_0000_0000_0000, -62, -1)
+        );
+
+        assert_eq!(
+            <F32 as NumFloat>::epsilon().to_bits(),
+            f32::EPSILON.to_bits()
+        );
+        assert_eq!(<F32 as NumFloat>::max_value().to_bits(), f32::MAX.to_bits());
+        assert_eq!(<F32 as NumFloat>::min_value().to_bits(), f32::MIN.to_bits());
+        assert_eq!(
+            <F32 as NumFloat>::min_positive_value().to_bits(),
+            f32::MIN_POSITIVE.to_bits()
+        );
+        assert_eq!(<F16 as NumFloat>::epsilon().to_bits(), 0x1400);
+        assert_eq!(<F16 as NumFloat>::max_value().to_bits(), 0x7bff);
+        assert_eq!(<F128 as NumFloat>::epsilon().to_bits(), 0x3f8f << 112);
+    }
+
+    #[test]
+    fn num_const() {
+        assert_eq!(F32::PI().to_bits(), std::f32::consts::PI.to_bits());
+        assert_eq!(F64::PI().to_bits(), std::f64::consts::PI.to_bits());
+        assert_eq!(F64::E().to_bits(), std::f64::consts::E.to_bits());
+        assert_eq!(F64::LN_2().to_bits(), std::f64::consts::LN_2.to_bits());
+        assert_eq!(F64::SQRT_2().to_bits(), std::f64::consts::SQRT_2.to_bits());
+        assert_eq!(F16::PI().to_bits(), 0x4248);
+        assert_eq!(BF16::PI().to_bits(), 0x4049);
+    }
+
+    #[test]
+    fn num_cast() {
+        let a = F64::from_f64(-2.75);
+        assert_eq!(ToPrimitive::to_i64(&a), Some(-2));
+        assert_eq!(ToPrimitive::to_u64(&a), None);
+        assert_eq!(ToPrimitive::to_f32(&a), Some(-2.75));
+        assert_eq!(ToPrimitive::to_i32(&F64::nan()), None);
+        assert_eq!(ToPrimitive::to_i64(&F64::from_f64(1e30)), None);
+        assert_eq!(ToPrimitive::to_u64(&F64::from_f64(-0.5)), Some(0));
+
+        let mut flag = ExceptionFlags::default();
+        flag.set();
+        let _d = ToPrimitive::to_i64(&F64::nan());
+        flag.get();
+        assert!(!flag.is_invalid());
+
+        let d = <F16 as NumCast>::from(65504u32).unwrap();
+        assert_eq!(d.to_bits(), 0x7bff);
+        let d = <F128 as NumCast>::from(u64::MAX).unwrap();
+        assert_eq!(d.to_bits(), 0x403efffffffffffffffe000000000000);
+        let d = <F128 as NumCast>::from(i64::MIN + 1).unwrap();
+        assert_eq!(ToPrimitive::to_i64(&d), Some(i64::MIN + 1));
+        let d = <BF16 as NumCast>::from((1u64 << 63) + (1 << 55) + 1).unwrap();
+        assert_eq!(d.to_bits(), 0x5f01);
+        let d = <F64 as NumCast>::from(-0.0f64).unwrap();
+        assert_eq!(d.to_bits(), 0x8000000000000000);
+        let d = <F32 as NumCast>::from(-2.5f64).unwrap();
+        assert_eq!(d.to_bits(), (-2.5f32).to_bits());
+        let d = <F128 as NumCast>::from(1e300f64).unwrap();
+        assert_eq!(ToPrimitive::to_f64(&d), Some(1e300));
+        assert!(<F16 as NumCast>::from(f32::NAN).unwrap().is_nan());
+        let d = <F128 as FromPrimitive>::from_u64(u64::MAX).unwrap();
+        assert_eq!(ToPrimitive::to_u64(&d), Some(u64::MAX));
+        let d = <F128 as FromPrimitive>::from_i128(i128::MIN).unwrap();
+        assert_eq!(ToPrimitive::to_i128(&d), Some(i128::MIN));
+        assert_eq!(ToPrimitive::to_u128(&d), None);
+        assert_eq!(
+            ToPrimitive::to_u128(&F64::from_f64(1e30)),
+            Some(1e30 as u128)
+        );
+        // radix other than 10 is parsed by f64
+        let d = <F128 as Num>::from_str_radix("ffffffffffffffffff", 16).unwrap();
+        assert_eq!(d.to_bits(), 0x40470000000000000000000000000000);
+        let d = <F32 as Num>::from_str_radix("1.5", 10).unwrap();
+        assert_eq!(d.to_bits(), 1.5f32.to_bits());
+        assert!(<F32 as Num>::from_str_radix("x", 10).is_err());
+        assert!(Zero::is_zero(&F32::zero()));
+        assert_eq!(F32::one().to_bits(), 1.0f32.to_bits());
+    }
+
+    #[test]
+    fn num_powi() {
+        let a = F16::from_f32(300.0);
+        let (d, flag) = ExceptionFlags::capture(|| NumFloat::powi(a, 1));
+        assert_eq!(d.to_bits(), a.to_bits());
+        assert_eq!(flag, ExceptionFlags::default());
+        let (d, flag) = ExceptionFlags::capture(|| NumFloat::powi(F16::from_f32(3.0), 5));
+        assert_eq!(d.to_bits(), F16::from_f32(243.0).to_bits());
+        assert_eq!(flag, ExceptionFlags::default());
+        let d = NumFloat::powi(F16::from_f32(2.0), -3);
+        assert_eq!(d.to_bits(), F16::from_f32(0.125).to_bits());
+    }
+
+    #[test]
+    fn num_f128_via_f64() {
+        // transcendental functions of F128 have f64 precision and range
+        let one = F128::one();
+        let e = F128::from_f64(std::f64::consts::E);
+        assert_eq!(NumFloat::exp(one).to_bits(), e.to_bits());
+        assert_ne!(NumFloat::exp(one).to_bits(), F128::E().to_bits());
+        let d = NumFloat::exp(<F128 as Float>::from_u32(1000, RND));
+        assert!(Float::is_positive_infinity(&d));
+    }
+}
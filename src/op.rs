@@ -0,0 +1,290 @@
+use crate::trap::{self, Operation};
+use crate::{flush, round, Float, RoundingMode, Specialization};
+use num_traits::{NumCast, PrimInt, ToPrimitive, Zero};
+
+// Operations are computed by the compiled SoftFloat specialization,
+// and the results depending on the specialization are fixed by the active one.
+
+pub(crate) fn unary<F: Float, O: FnOnce(&F) -> F>(operation: Operation, a: &F, op: O) -> F {
+    trap::check(operation, &[bits(a)], || {
+        let a = &flush::input(a);
+        flush::output(|| {
+            let ret = op(a);
+            if ret.is_nan() {
+                let spec = Specialization::current();
+                if a.is_nan() {
+                    spec.propagate_nan(a, &F::from_bits(F::Payload::zero()))
+                } else {
+                    spec.default_nan()
+                }
+            } else {
+                ret
+            }
+        })
+    })
+}
+
+pub(crate) fn binary<F: Float, O: FnOnce(&F, &F) -> F>(
+    operation: Operation,
+    a: &F,
+    b: &F,
+    op: O,
+) -> F {
+    trap::check(operation, &[bits(a), bits(b)], || {
+        let (a, b) = (&flush::input(a), &flush::input(b));
+        flush::output(|| {
+            let ret = op(a, b);
+            if ret.is_nan() {
+                let spec = Specialization::current();
+                if a.is_nan() || b.is_nan() {
+                    spec.propagate_nan(a, b)
+                } else {
+                    spec.default_nan()
+                }
+            } else {
+                ret
+            }
+        })
+    })
+}
+
+pub(crate) fn fused_mul_add<F: Float, O: FnOnce(&F, &F, &F) -> F>(a: &F, b: &F, c: &F, op: O) -> F {
+    trap::check(Operation::FusedMulAdd, &[bits(a), bits(b), bits(c)], || {
+        let (a, b, c) = (&flush::input(a), &flush::input(b), &flush::input(c));
+        flush::output(|| {
+            let ret = op(a, b, c);
+            if ret.is_nan() {
+                let spec = Specialization::current();
+                let infinite = |x: &F| x.is_positive_infinity() || x.is_negative_infinity();
+                let z = if a.is_nan() || b.is_nan() {
+                    spec.propagate_nan(a, b)
+                } else if infinite(a) || infinite(b) {
+                    if a.is_zero() || b.is_zero() {
+                        spec.default_nan()
+                    } else {
+                        let mut z = F::positive_infinity();
+                        z.set_sign(a.sign() ^ b.sign());
+                        z
+                    }
+                } else {
+                    F::from_bits(F::Payload::zero())
+                };
+                if z.is_nan() || c.is_nan() {
+                    spec.propagate_nan(&z, c)
+                } else {
+                    spec.default_nan()
+                }
+            } else {
+                ret
+            }
+        })
+    })
+}
+
+pub(crate) fn convert<F: Float, G: Float, O: FnOnce(&F) -> G>(a: &F, op: O) -> G {
+    trap::check(Operation::Convert, &[bits(a)], || {
+        let a = &flush::input(a);
+        flush::output(|| {
+            let ret = op(a);
+            if a.is_nan() {
+                Specialization::current().convert_nan(a)
+            } else {
+                ret
+            }
+        })
+    })
+}
+
+pub(crate) fn compare<F: Float, O: FnOnce(&F, &F) -> bool>(a: &F, b: &F, op: O) -> bool {
+    trap::check(Operation::Compare, &[bits(a), bits(b)], || {
+        op(&flush::input(a), &flush::input(b))
+    })
+}
+
+pub(crate) fn to_int<F: Float, I: PrimInt, O: FnOnce(&F) -> I>(a: &F, op: O) -> I {
+    trap::check(Operation::ToInt, &[bits(a)], || {
+        let a = &flush::input(a);
+        let spec = Specialization::current();
+        if spec == Specialization::compiled() {
+            return op(a);
+        }
+        let flags = unsafe { softfloat_sys::softfloat_exceptionFlags_read_helper() };
+        unsafe { softfloat_sys::softfloat_exceptionFlags_write_helper(0) };
+        let ret = op(a);
+        let op_flags = unsafe { softfloat_sys::softfloat_exceptionFlags_read_helper() };
+        unsafe { softfloat_sys::softfloat_exceptionFlags_write_helper(flags | op_flags) };
+        if op_flags & softfloat_sys::softfloat_flag_invalid != 0 {
+            spec.invalid_int(a.is_nan(), a.is_negative())
+        } else {
+            ret
+        }
+    })
+}
+
+// conversion to an integer type through `op` converting to a wider one
+pub(crate) fn to_narrow_int<F: Float + ?Sized, I: PrimInt, J: PrimInt, O: FnOnce(&F) -> J>(
+    a: &F,
+    op: O,
+) -> I {
+    trap::check(Operation::ToInt, &[bits(a)], || {
+        let flags = unsafe { softfloat_sys::softfloat_exceptionFlags_read_helper() };
+        unsafe { softfloat_sys::softfloat_exceptionFlags_write_helper(0) };
+        let ret = op(a);
+        let op_flags = unsafe { softfloat_sys::softfloat_exceptionFlags_read_helper() };
+        match <I as NumCast>::from(ret) {
+            Some(x) if op_flags & softfloat_sys::softfloat_flag_invalid == 0 => {
+                unsafe { softfloat_sys::softfloat_exceptionFlags_write_helper(flags | op_flags) };
+                x
+            }
+            _ => {
+                let flags = flags | softfloat_sys::softfloat_flag_invalid;
+                unsafe { softfloat_sys::softfloat_exceptionFlags_write_helper(flags) };
+                Specialization::current().invalid_int(a.is_nan(), a.is_negative())
+            }
+        }
+    })
+}
+
+// conversion to an integer type wider than 64 bits, which is not supported by SoftFloat
+pub(crate) fn to_wide_int<F: Float + ?Sized, I: PrimInt>(
+    a: &F,
+    rnd: RoundingMode,
+    exact: bool,
+) -> I {
+    trap::check(Operation::ToInt, &[bits(a)], || {
+        let zero = flush::is_flushed(a);
+        let ret = a.decompose().and_then(|(sign, e, f)| {
+            let f = if zero { 0 } else { f };
+            let (abs, inexact) = round::round_int(sign, e, f, rnd)?;
+            let ret = if sign && abs != 0 {
+                if abs > 1 << 127 {
+                    return None;
+                }
+                <I as NumCast>::from((abs as i128).wrapping_neg())?
+            } else {
+                <I as NumCast>::from(abs)?
+            };
+            Some((ret, inexact))
+        });
+        match ret {
+            Some((x, inexact)) => {
+                if exact && inexact {
+                    unsafe {
+                        softfloat_sys::softfloat_raiseFlags(softfloat_sys::softfloat_flag_inexact)
+                    };
+                }
+                x
+            }
+            None => {
+                unsafe {
+                    softfloat_sys::softfloat_raiseFlags(softfloat_sys::softfloat_flag_invalid)
+                };
+                Specialization::current().invalid_int(a.is_nan(), a.is_negative())
+            }
+        }
+    })
+}
+
+// roundToIntegralExact through `round_to_integral`
+pub(crate) fn round_to_integral_exact<F: Float>(a: &F, rnd: RoundingMode) -> F {
+    unary(Operation::RoundToIntegral, a, |a| {
+        let ret = a.round_to_integral(rnd);
+        if !a.is_nan() && ret.to_bits() != a.to_bits() {
+            unsafe { softfloat_sys::softfloat_raiseFlags(softfloat_sys::softfloat_flag_inexact) };
+        }
+        ret
+    })
+}
+
+// remainder truncating the quotient like `%` of primitive floats, which is always exact
+pub(crate) fn fmod<F: Float>(a: &F, b: &F) -> F {
+    trap::check(Operation::Rem, &[bits(a), bits(b)], || {
+        let rnd = RoundingMode::TiesToEven;
+        let ret = a.rem(b, rnd);
+        if ret.is_nan() || ret.is_zero() || ret.sign() == a.sign() {
+            ret
+        } else if a.is_negative() {
+            ret.sub(b.abs(), rnd)
+        } else {
+            ret.add(b.abs(), rnd)
+        }
+    })
+}
+
+pub(crate) fn from_int<F: Float, O: FnOnce() -> F>(x: u128, op: O) -> F {
+    trap::check(Operation::FromInt, &[x], op)
+}
+
+pub(crate) fn bits<F: Float + ?Sized>(x: &F) -> u128 {
+    x.to_bits().to_u128().unwrap()
+}
+
+// NaN result of operations which are not computed by SoftFloat
+pub(crate) fn propagate_nan<F: Float>(a: &F, b: &F) -> F {
+    signal_nan(a, b);
+    Specialization::current().propagate_nan(a, b)
+}
+
+pub(crate) fn signal_nan<F: Float>(a: &F, b: &F) {
+    if a.is_signaling_nan() || b.is_signaling_nan() {
+        unsafe { softfloat_sys::softfloat_raiseFlags(softfloat_sys::softfloat_flag_invalid) };
+    }
+}
+
+// payload of NaN represented by `x`, which must be an integer less than the quiet bit
+pub(crate) fn payload<F: Float>(x: &F) -> Option<F::Payload> {
+    let (sign, e, f) = x.decompose()?;
+    let payload = if f == 0 {
+        0
+    } else if sign {
+        return None;
+    } else if e >= 0 {
+        f.checked_shl(e as u32).filter(|x| x >> e == f)?
+    } else if (f.trailing_zeros() as i32) < -e {
+        return None;
+    } else {
+        f >> -e
+    };
+    if payload < 1 << (F::EXPONENT_POS - 1) {
+        <F::Payload as NumCast>::from(payload)
+    } else {
+        None
+    }
+}
+
+// minimum and maximum operations of IEEE 754-2019 §9.6
+pub(crate) fn min_max<F: Float>(a: &F, b: &F, max: bool, magnitude: bool, number: bool) -> F {
+    trap::check(Operation::MinMax, &[bits(a), bits(b)], || {
+        let (a, b) = (&flush::input(a), &flush::input(b));
+        let copy = |x: &F| F::from_bits(x.to_bits());
+        if a.is_nan() || b.is_nan() {
+            return if number && !a.is_nan() {
+                signal_nan(a, b);
+                copy(a)
+            } else if number && !b.is_nan() {
+                signal_nan(a, b);
+                copy(b)
+            } else {
+                propagate_nan(a, b)
+            };
+        }
+        let (x, y) = if magnitude {
+            (a.abs(), b.abs())
+        } else {
+            (copy(a), copy(b))
+        };
+        let a_first = if x.lt_quiet(&y) {
+            !max
+        } else if y.lt_quiet(&x) {
+            max
+        } else {
+            // equal values are ordered by sign, so -0 is less than +0
+            a.is_negative() != max
+        };
+        if a_first {
+            copy(a)
+        } else {
+            copy(b)
+        }
+    })
+}
@@ -0,0 +1,467 @@
+use crate::bigint::BigUint;
+use crate::round::round_pack;
+use crate::{Float, RoundingMode, BF16, F128, F16, F32, F64};
+use num_traits::{FloatErrorKind, NumCast, One, ParseFloatError, Zero};
+use std::str::FromStr;
+
+// Decimal strings are converted to binary exactly by big integers, and rounded once.
+// Significant digits over `MAX_DIGITS` only affect the result as sticky digit,
+// because any halfway point of F128 has less significant digits.
+// Hexadecimal strings like `0x1.8p3` are rounded from the binary digits directly.
+
+const MAX_DIGITS: usize = 12000;
+const MAX_EXPONENT: i64 = 5000;
+
+// precision of the binary significand before rounding, which is larger than F128's
+const PRECISION: usize = 125;
+
+fn invalid() -> ParseFloatError {
+    ParseFloatError {
+        kind: FloatErrorKind::Invalid,
+    }
+}
+
+pub(crate) fn parse<F: Float>(s: &str, rnd: RoundingMode) -> Result<F, ParseFloatError> {
+    if s.is_empty() {
+        return Err(ParseFloatError {
+            kind: FloatErrorKind::Empty,
+        });
+    }
+    let (sign, s) = match s.as_bytes()[0] {
+        b'+' => (false, &s[1..]),
+        b'-' => (true, &s[1..]),
+        _ => (false, s),
+    };
+    let lower = s.to_ascii_lowercase();
+    let sign_bits = if sign {
+        F::Payload::one()
+    } else {
+        F::Payload::zero()
+    };
+
+    if lower == "inf" || lower == "infinity" {
+        let mut ret = F::positive_infinity();
+        ret.set_sign(sign_bits);
+        Ok(ret)
+    } else if let Some(payload) = lower.strip_prefix("nan") {
+        let mut ret = parse_nan::<F>(payload, true)?;
+        ret.set_sign(sign_bits);
+        Ok(ret)
+    } else if let Some(payload) = lower.strip_prefix("snan") {
+        let mut ret = parse_nan::<F>(payload, false)?;
+        ret.set_sign(sign_bits);
+        Ok(ret)
+    } else if let Some(s) = s.strip_prefix("0x").or_else(|| s.strip_prefix("0X")) {
+        parse_hex(sign, s, rnd)
+    } else {
+        parse_decimal(sign, s, rnd)
+    }
+}
+
+fn parse_nan<F: Float>(s: &str, quiet: bool) -> Result<F, ParseFloatError> {
+    if s.is_empty() {
+        return Ok(if quiet {
+            F::quiet_nan()
+        } else {
+            F::signaling_nan()
+        });
+    }
+    let s = s
+        .strip_prefix('(')
+        .and_then(|x| x.strip_suffix(')'))
+        .ok_or_else(invalid)?;
+    let (s, radix) = match s.strip_prefix("0x") {
+        Some(s) => (s, 16),
+        None => (s, 10),
+    };
+    if s.is_empty() || !s.chars().all(|x| x.is_digit(radix)) {
+        return Err(invalid());
+    }
+    let payload = u128::from_str_radix(s, radix).map_err(|_| invalid())?;
+
+    let quiet_bit = 1u128 << (F::EXPONENT_POS - 1);
+    if payload >= quiet_bit || (!quiet && payload == 0) {
+        return Err(invalid());
+    }
+    let fraction = if quiet { quiet_bit | payload } else { payload };
+    let mut ret = F::from_bits(<F::Payload as NumCast>::from(fraction).unwrap());
+    ret.set_exponent(F::EXPONENT_BIT);
+    Ok(ret)
+}
+
+fn parse_exponent(s: &str) -> Result<i64, ParseFloatError> {
+    let (neg, s) = match s.as_bytes().first() {
+        Some(b'+') => (false, &s[1..]),
+        Some(b'-') => (true, &s[1..]),
+        _ => (false, s),
+    };
+    if s.is_empty() || !s.bytes().all(|x| x.is_ascii_digit()) {
+        return Err(invalid());
+    }
+    // saturate huge exponents, which overflow or underflow anyway
+    let x = s
+        .bytes()
+        .fold(0i64, |acc, x| (acc * 10 + (x - b'0') as i64).min(1 << 40));
+    Ok(if neg { -x } else { x })
+}
+
+fn parse_hex<F: Float>(sign: bool, s: &str, rnd: RoundingMode) -> Result<F, ParseFloatError> {
+    let (mantissa, exponent) = match s.find(['p', 'P']) {
+        Some(i) => (&s[..i], Some(&s[i + 1..])),
+        None => (s, None),
+    };
+    let (int, frac) = match mantissa.find('.') {
+        Some(i) => (&mantissa[..i], &mantissa[i + 1..]),
+        None => (mantissa, ""),
+    };
+    let is_digits = |x: &str| x.bytes().all(|x| x.is_ascii_hexdigit());
+    if (int.is_empty() && frac.is_empty()) || !is_digits(int) || !is_digits(frac) {
+        return Err(invalid());
+    }
+    let mut exp2 = match exponent {
+        Some(x) => parse_exponent(x)?,
+        None => 0,
+    };
+    exp2 -= 4 * frac.len() as i64;
+
+    // digits over 124 bits only affect the result as sticky bit
+    let mut sig = 0u128;
+    let mut sticky = false;
+    for x in int.chars().chain(frac.chars()) {
+        let x = x.to_digit(16).unwrap() as u128;
+        if sig >> 120 == 0 {
+            sig = (sig << 4) | x;
+        } else {
+            sticky |= x != 0;
+            exp2 += 4;
+        }
+    }
+    let exp2 = exp2.clamp(-(1 << 20), 1 << 20);
+    Ok(round_pack(sign, exp2 as i32, sig, sticky, rnd))
+}
+
+fn parse_decimal<F: Float>(sign: bool, s: &str, rnd: RoundingMode) -> Result<F, ParseFloatError> {
+    let (mantissa, exponent) = match s.find(['e', 'E']) {
+        Some(i) => (&s[..i], Some(&s[i + 1..])),
+        None => (s, None),
+    };
+    let (int, frac) = match mantissa.find('.') {
+        Some(i) => (&mantissa[..i], &mantissa[i + 1..]),
+        None => (mantissa, ""),
+    };
+    let is_digits = |x: &str| x.bytes().all(|x| x.is_ascii_digit());
+    if (int.is_empty() && frac.is_empty()) || !is_digits(int) || !is_digits(frac) {
+        return Err(invalid());
+    }
+
+    let mut exp10 = match exponent {
+        Some(x) => parse_exponent(x)?,
+        None => 0,
+    };
+    exp10 -= frac.len() as i64;
+
+    let digits: Vec<u8> = int.bytes().chain(frac.bytes()).map(|x| x - b'0').collect();
+    let digits = match digits.iter().position(|x| *x != 0) {
+        Some(i) => &digits[i..],
+        None => return Ok(round_pack(sign, 0, 0, false, rnd)),
+    };
+    let last = digits.iter().rposition(|x| *x != 0).unwrap();
+    exp10 += (digits.len() - last - 1) as i64;
+    let mut digits = digits[..=last].to_vec();
+    if digits.len() > MAX_DIGITS {
+        // dropped digits are nonzero because trailing zeros are removed
+        exp10 += (digits.len() - MAX_DIGITS) as i64 - 1;
+        digits.truncate(MAX_DIGITS);
+        digits.push(1);
+    }
+
+    let adjusted = exp10 + digits.len() as i64 - 1;
+    if adjusted > MAX_EXPONENT {
+        return Ok(round_pack(sign, 4 * MAX_EXPONENT as i32, 1, false, rnd));
+    } else if adjusted < -MAX_EXPONENT {
+        return Ok(round_pack(sign, -4 * MAX_EXPONENT as i32, 1, true, rnd));
+    }
+
+    let mut d = BigUint::from_u128(0);
+    for chunk in digits.chunks(9) {
+        let x = chunk.iter().fold(0, |acc, x| acc * 10 + *x as u32);
+        d.mul_add_small(10u32.pow(chunk.len() as u32), x);
+    }
+
+    // value = d * 10^exp10 = d * 5^exp10 * 2^exp10
+    let (sig, sticky, exp2) = if exp10 >= 0 {
+        d.mul_pow5(exp10 as u32);
+        let shift = d.bit_len().saturating_sub(PRECISION);
+        let (sig, sticky) = d.shr_u128(shift);
+        (sig, sticky, exp10 + shift as i64)
+    } else {
+        let mut m = BigUint::from_u128(1);
+        m.mul_pow5(-exp10 as u32);
+        let shift = (PRECISION + m.bit_len()) as i64 - d.bit_len() as i64;
+        let (sig, sticky) = if shift >= 0 {
+            d.shl(shift as usize).div_u128(&m)
+        } else {
+            d.div_u128(&m.shl(-shift as usize))
+        };
+        (sig, sticky, exp10 - shift)
+    };
+    Ok(round_pack(sign, exp2 as i32, sig, sticky, rnd))
+}
+
+macro_rules! impl_from_str {
+    ($t:ty) => {
+        impl FromStr for $t {
+            type Err = ParseFloatError;
+
+            fn from_str(s: &str) -> Result<Self, Self::Err> {
+                Self::from_str_rounded(s, RoundingMode::TiesToEven).map(|x| x.0)
+            }
+        }
+    };
+}
+
+impl_from_str!(F16);
+impl_from_str!(BF16);
+impl_from_str!(F32);
+impl_from_str!(F64);
+impl_from_str!(F128);
+
+#[cfg(test)]
+mod tests {
+    use crate::{ExceptionFlags, Float, RoundingMode, BF16, F128, F16, F32, F64};
+
+    #[test]
+    fn parse_f32() {
+        for s in &[
+            "0",
+            "-0",
+            "1",
+            "1.5",
+            "0.1",
+            "-3.14159265358979",
+            "1e10",
+            "1E-10",
+            "123.456e+7",
+            ".5",
+            "5.",
+            "3.4028235e38",
+            "1.17549435e-38",
+            "1e-45",
+            "7e-46",
+            "0.0000001",
+            "16777217",
+            "16777219",
+        ] {
+            let d: F32 = s.parse().unwrap();
+            let e: f32 = s.parse().unwrap();
+            assert_eq!(d.to_bits(), e.to_bits(), "{}", s);
+        }
+    }
+
+    #[test]
+    fn parse_f64() {
+        for s in &[
+            "2.2250738585072011e-308",
+            "2.2250738585072012e-308",
+            "4.9406564584124654e-324",
+            "2.4703282292062327e-324",
+            "2.4703282292062328e-324",
+            "1.7976931348623157e308",
+            "1.7976931348623158e308",
+            "9007199254740993",
+            "9007199254740993.0000000000000000000000000000000000000000000000001",
+            "0.30000000000000004441",
+        ] {
+            let d: F64 = s.parse().unwrap();
+            let e: f64 = s.parse().unwrap();
+            assert_eq!(d.to_bits(), e.to_bits(), "{}", s);
+        }
+    }
+
+    #[test]
+    fn parse_long() {
+        // halfway between 1 and the next F64 followed by many zeros
+        let half = "1.00000000000000011102230246251565404236316680908203125";
+        let zeros = "0".repeat(20000);
+        let d: F64 = format!("{}{}", half, zeros).parse().unwrap();
+        assert_eq!(d.to_bits(), 0x3ff0000000000000);
+        let d: F64 = format!("{}{}1", half, zeros).parse().unwrap();
+        assert_eq!(d.to_bits(), 0x3ff0000000000001);
+        let d: F64 = format!("0.{}1", zeros).parse().unwrap();
+        assert_eq!(d.to_bits(), 0);
+        let d: F64 = format!("1{}e-20000", zeros).parse().unwrap();
+        assert_eq!(d.to_bits(), 0x3ff0000000000000);
+    }
+
+    #[test]
+    fn parse_rounding_mode() {
+        for rnd in &[
+            RoundingMode::TiesToEven,
+            RoundingMode::TowardZero,
+            RoundingMode::TowardNegative,
+            RoundingMode::TowardPositive,
+            RoundingMode::TiesToAway,
+            RoundingMode::ToOdd,
+        ] {
+            for s in &[
+                "0.1", "-0.1", "65504", "65519", "65520", "-1e10", "1e-8", "3e-8", "-2.98e-8",
+            ] {
+                let (d, flag) = F16::from_str_rounded(s, *rnd).unwrap();
+                // these values are exact or not near halfway points in F64
+                let e: f64 = s.parse().unwrap();
+                let (e, e_flag) = F64::from_bits(e.to_bits()).to_f16_with_flags(*rnd);
+                assert_eq!(d.to_bits(), e.to_bits(), "{} {:?}", s, rnd);
+                assert_eq!(flag.to_bits(), e_flag.to_bits(), "{} {:?}", s, rnd);
+            }
+        }
+    }
+
+    #[test]
+    fn parse_flags() {
+        let rnd = RoundingMode::TiesToEven;
+        let (d, flag) = F32::from_str_rounded("0.5", rnd).unwrap();
+        assert_eq!(d.to_bits(), 0x3f000000);
+        assert_eq!(flag.to_bits(), 0);
+        let (_, flag) = F32::from_str_rounded("0.1", rnd).unwrap();
+        assert!(flag.is_inexact() && !flag.is_underflow());
+        let (d, flag) = F32::from_str_rounded("1e39", rnd).unwrap();
+        assert!(d.is_positive_infinity());
+        assert!(flag.is_overflow() && flag.is_inexact());
+        let (d, flag) = F32::from_str_rounded("-1e-50", rnd).unwrap();
+        assert!(d.is_negative_zero());
+        assert!(flag.is_underflow() && flag.is_inexact());
+        let (d, flag) = BF16::from_str_rounded("1e99999999999999999999", rnd).unwrap();
+        assert!(d.is_positive_infinity());
+        assert!(flag.is_overflow());
+        let (d, flag) = F128::from_str_rounded("1e-99999999999999999999", rnd).unwrap();
+        assert!(d.is_positive_zero());
+        assert!(flag.is_underflow());
+
+        // caller's flags are not modified
+        let mut flag = ExceptionFlags::default();
+        flag.set();
+        let _: F32 = "0.1".parse().unwrap();
+        flag.get();
+        assert!(!flag.is_inexact());
+    }
+
+    #[test]
+    fn parse_f128() {
+        let rnd = RoundingMode::TiesToEven;
+        let d: F128 = "1".parse().unwrap();
+        assert_eq!(d.to_bits(), 0x3fff << 112);
+        let d: F128 = "0.1".parse().unwrap();
+        assert_eq!(d.to_bits(), 0x3ffb999999999999999999999999999a);
+        let (d, _) = F128::from_str_rounded("0.1", RoundingMode::TowardZero).unwrap();
+        assert_eq!(d.to_bits(), 0x3ffb9999999999999999999999999999);
+        let d: F128 = "1.189731495357231765085759326628007e4932".parse().unwrap();
+        assert_eq!(d.to_bits(), 0x7ffeffffffffffffffffffffffffffff);
+        let d: F128 = "6.475175119438025110924438958227646552e-4966"
+            .parse()
+            .unwrap();
+        assert_eq!(d.to_bits(), 0x1);
+        let (d, flag) = F128::from_str_rounded("3.2e-4966", rnd).unwrap();
+        assert_eq!(d.to_bits(), 0x0);
+        assert!(flag.is_underflow());
+        let d: F128 = "3.3e-4966".parse().unwrap();
+        assert_eq!(d.to_bits(), 0x1);
+        let d = F64::from_bits(0x3fb999999999999a).to_f128(rnd);
+        let e: F128 = "0.1000000000000000055511151231257827021181583404541015625"
+            .parse()
+            .unwrap();
+        assert_eq!(d.to_bits(), e.to_bits());
+    }
+
+    #[test]
+    fn parse_hex() {
+        let d: F32 = "-0x1.91eb86p+1".parse().unwrap();
+        assert_eq!(d.to_bits(), 0xc048f5c3);
+        let d: F64 = "0x1.8p3".parse().unwrap();
+        assert_eq!(d.to_bits(), 12.0f64.to_bits());
+        let d: F64 = "0X.8P-1".parse().unwrap();
+        assert_eq!(d.to_bits(), 0.25f64.to_bits());
+        let d: F64 = "0x10".parse().unwrap();
+        assert_eq!(d.to_bits(), 16.0f64.to_bits());
+        let d: F64 = "0x0.0000000000001p-1022".parse().unwrap();
+        assert_eq!(d.to_bits(), 1);
+        let d: F128 = "0x1.0000000000000000000000000001p+0".parse().unwrap();
+        assert_eq!(d.to_bits(), 0x3fff << 112 | 1);
+
+        // rounded to the precision of the target format
+        let rnd = RoundingMode::TiesToEven;
+        let (d, flag) = F16::from_str_rounded("0x1.002p0", rnd).unwrap();
+        assert_eq!(d.to_bits(), 0x3c00);
+        assert!(flag.is_inexact());
+        let (d, _) = F16::from_str_rounded("0x1.006p0", rnd).unwrap();
+        assert_eq!(d.to_bits(), 0x3c02);
+        let d: F16 = "0x1.00200000000000000000000000000000000001p0"
+            .parse()
+            .unwrap();
+        assert_eq!(d.to_bits(), 0x3c01);
+        let (d, _) = BF16::from_str_rounded("0x1.ffp0", rnd).unwrap();
+        assert_eq!(d.to_bits(), 0x4000);
+        let (d, _) = BF16::from_str_rounded("0x1.ffp0", RoundingMode::TowardZero).unwrap();
+        assert_eq!(d.to_bits(), 0x3fff);
+        let (d, flag) = F16::from_str_rounded("0x1p-25", rnd).unwrap();
+        assert_eq!(d.to_bits(), 0x0000);
+        assert!(flag.is_underflow());
+        let (d, flag) = F16::from_str_rounded("0x1p16", rnd).unwrap();
+        assert!(d.is_positive_infinity());
+        assert!(flag.is_overflow());
+        let (d, flag) = F32::from_str_rounded("-0x1p-99999999999999999999", rnd).unwrap();
+        assert!(d.is_negative_zero());
+        assert!(flag.is_underflow());
+
+        for s in &["0x", "0x.", "0xp1", "0x1p", "0x1.g", "0x1p1.5", "0x1e+1p0"] {
+            assert!(s.parse::<F32>().is_err(), "{}", s);
+        }
+    }
+
+    #[test]
+    fn parse_special() {
+        let d: F32 = "inf".parse().unwrap();
+        assert_eq!(d.to_bits(), 0x7f800000);
+        let d: F32 = "-Infinity".parse().unwrap();
+        assert_eq!(d.to_bits(), 0xff800000);
+        let d: F32 = "NaN".parse().unwrap();
+        assert_eq!(d.to_bits(), 0x7fc00000);
+        let d: F32 = "-nan(0x1234)".parse().unwrap();
+        assert_eq!(d.to_bits(), 0xffc01234);
+        let d: F32 = "nan(10)".parse().unwrap();
+        assert_eq!(d.to_bits(), 0x7fc0000a);
+        let d: F32 = "sNaN".parse().unwrap();
+        assert_eq!(d.to_bits(), 0x7fa00000);
+        assert!(d.is_signaling_nan());
+        let d: F16 = "snan(0x1ff)".parse().unwrap();
+        assert_eq!(d.to_bits(), 0x7dff);
+        let d: F128 = "nan(0x123456789abcdef0123456789)".parse().unwrap();
+        assert_eq!(d.to_bits(), 0x7fff800123456789abcdef0123456789);
+    }
+
+    #[test]
+    fn parse_error() {
+        for s in &[
+            "",
+            "+",
+            "-",
+            ".",
+            "e5",
+            "1e",
+            "1e+",
+            "1.2.3",
+            "1x",
+            " 1",
+            "infi",
+            "nan(",
+            "nan()",
+            "nan(0x)",
+            "nan(0x200)",
+            "snan(0)",
+            "nan(-1)",
+            "1_000",
+        ] {
+            assert!(s.parse::<F16>().is_err(), "{}", s);
+        }
+        assert!(F32::from_str_rounded("x", RoundingMode::TiesToEven).is_err());
+    }
+}
@@ -0,0 +1,261 @@
+use crate::{Float, RoundingMode, Tininess};
+use num_traits::{NumCast, One, ToPrimitive, Zero};
+
+// Rounding of exact binary values which SoftFloat can't take as operands.
+// Results and exception flags are same as SoftFloat's roundPack functions.
+
+#[derive(Copy, Clone, PartialEq, Eq)]
+enum Rest {
+    Zero,
+    BelowHalf,
+    Half,
+    AboveHalf,
+}
+
+/// Returns `sig >> shift` and the dropped bits
+fn split(sig: u128, sticky: bool, shift: i32) -> (u128, Rest) {
+    if shift <= 0 {
+        let rest = if sticky { Rest::BelowHalf } else { Rest::Zero };
+        return (sig << -shift, rest);
+    }
+    if shift > 128 {
+        return (0, Rest::BelowHalf);
+    }
+    let kept = if shift == 128 { 0 } else { sig >> shift };
+    let dropped = if shift == 128 {
+        sig
+    } else {
+        sig & ((1 << shift) - 1)
+    };
+    let half = 1 << (shift - 1);
+    let rest = match dropped.cmp(&half) {
+        _ if dropped == 0 && !sticky => Rest::Zero,
+        std::cmp::Ordering::Less => Rest::BelowHalf,
+        std::cmp::Ordering::Equal if !sticky => Rest::Half,
+        _ => Rest::AboveHalf,
+    };
+    (kept, rest)
+}
+
+fn increment(kept: u128, rest: Rest, sign: bool, rnd: RoundingMode) -> bool {
+    match rnd {
+        RoundingMode::TiesToEven => {
+            rest == Rest::AboveHalf || (rest == Rest::Half && kept & 1 == 1)
+        }
+        RoundingMode::TiesToAway => rest == Rest::AboveHalf || rest == Rest::Half,
+        RoundingMode::TowardZero => false,
+        RoundingMode::TowardNegative => sign && rest != Rest::Zero,
+        RoundingMode::TowardPositive => !sign && rest != Rest::Zero,
+        RoundingMode::ToOdd => kept & 1 == 0 && rest != Rest::Zero,
+    }
+}
+
+/// Rounds `(-1)^sign * sig * 2^exp` to integer, and returns its magnitude and whether it is inexact
+///
+/// Returns `None` if the magnitude doesn't fit in `u128`.
+pub(crate) fn round_int(
+    sign: bool,
+    exp: i32,
+    sig: u128,
+    rnd: RoundingMode,
+) -> Option<(u128, bool)> {
+    if sig == 0 {
+        return Some((0, false));
+    }
+    if exp >= 0 {
+        let ret = sig.checked_shl(exp as u32).filter(|x| x >> exp == sig)?;
+        return Some((ret, false));
+    }
+    let (kept, rest) = split(sig, false, -exp);
+    let ret = kept.checked_add(increment(kept, rest, sign, rnd) as u128)?;
+    Some((ret, rest != Rest::Zero))
+}
+
+/// Rounds `(-1)^sign * sig * 2^exp` to `F`, and raises exception flags
+///
+/// `sticky` means there are nonzero bits below `sig`, so `sig` must not be 0 if `sticky` is set.
+pub(crate) fn round_pack<F: Float>(
+    sign: bool,
+    exp: i32,
+    sig: u128,
+    sticky: bool,
+    rnd: RoundingMode,
+) -> F {
+    let sign_bits = if sign {
+        F::Payload::one()
+    } else {
+        F::Payload::zero()
+    };
+    let mut ret = F::from_bits(F::Payload::zero());
+    ret.set_sign(sign_bits);
+    if sig == 0 {
+        return ret;
+    }
+
+    let prec = F::EXPONENT_POS as i32 + 1;
+    let bias = (F::EXPONENT_BIT.to_i32().unwrap()) >> 1;
+    let emin = 1 - bias;
+    let emax = bias;
+
+    // exponent of the most significant bit
+    let e = exp + 127 - sig.leading_zeros() as i32;
+    let mut flags = 0;
+
+    let overflow = |flags: &mut u8| -> F {
+        *flags |= softfloat_sys::softfloat_flag_overflow | softfloat_sys::softfloat_flag_inexact;
+        let inf = match rnd {
+            RoundingMode::TiesToEven | RoundingMode::TiesToAway => true,
+            RoundingMode::TowardZero | RoundingMode::ToOdd => false,
+            RoundingMode::TowardNegative => sign,
+            RoundingMode::TowardPositive => !sign,
+        };
+        let mut ret = if inf {
+            F::positive_infinity()
+        } else {
+            F::from_bits((F::EXPONENT_BIT << F::EXPONENT_POS) - F::Payload::one())
+        };
+        ret.set_sign(sign_bits);
+        ret
+    };
+
+    if e > emax {
+        let ret = overflow(&mut flags);
+        unsafe { softfloat_sys::softfloat_raiseFlags(flags) };
+        return ret;
+    }
+
+    let lsb = (e - prec + 1).max(emin - prec + 1);
+    let (kept, rest) = split(sig, sticky, lsb - exp);
+    let mut kept = kept + increment(kept, rest, sign, rnd) as u128;
+    let mut lsb = lsb;
+    if kept == 1 << prec {
+        kept >>= 1;
+        lsb += 1;
+    }
+
+    if rest != Rest::Zero {
+        flags |= softfloat_sys::softfloat_flag_inexact;
+        let tiny = if e >= emin {
+            false
+        } else if Tininess::current() == Tininess::BeforeRounding || e < emin - 1 {
+            true
+        } else {
+            // tininess after rounding: rounded to the precision with unbounded exponent
+            let (kept, rest) = split(sig, sticky, e - prec + 1 - exp);
+            kept + (increment(kept, rest, sign, rnd) as u128) < 1 << prec
+        };
+        if tiny {
+            flags |= softfloat_sys::softfloat_flag_underflow;
+        }
+    }
+
+    let ret = if kept >> (prec - 1) == 0 {
+        // subnormal or zero
+        let mut ret = F::from_bits(<F::Payload as NumCast>::from(kept).unwrap());
+        ret.set_sign(sign_bits);
+        ret
+    } else if lsb + prec - 1 > emax {
+        overflow(&mut flags)
+    } else {
+        let exponent = (lsb + prec - 1 + bias) as u128;
+        let bits = (exponent << F::EXPONENT_POS) | (kept & ((1 << (prec - 1)) - 1));
+        let mut ret = F::from_bits(<F::Payload as NumCast>::from(bits).unwrap());
+        ret.set_sign(sign_bits);
+        ret
+    };
+    unsafe { softfloat_sys::softfloat_raiseFlags(flags) };
+    ret
+}
+
+#[cfg(test)]
+mod tests {
+    use super::{round_int, round_pack};
+    use crate::{ExceptionFlags, Float, RoundingMode, F128, F16, F32};
+
+    #[test]
+    fn round_pack_f32() {
+        let rnd = RoundingMode::TiesToEven;
+        // 1 + 2^-24 is a tie
+        let (d, flag) =
+            ExceptionFlags::capture(|| round_pack::<F32>(false, -24, (1 << 24) + 1, false, rnd));
+        assert_eq!(d.to_bits(), 0x3f800000);
+        assert_eq!(flag.to_bits(), 0x1);
+        let (d, _) =
+            ExceptionFlags::capture(|| round_pack::<F32>(false, -24, (1 << 24) + 1, true, rnd));
+        assert_eq!(d.to_bits(), 0x3f800001);
+        let d = round_pack::<F32>(true, -24, (1 << 24) + 3, false, rnd);
+        assert_eq!(d.to_bits(), 0xbf800002);
+        let d = round_pack::<F32>(true, -24, (1 << 24) + 1, false, RoundingMode::TiesToAway);
+        assert_eq!(d.to_bits(), 0xbf800001);
+    }
+
+    #[test]
+    fn round_pack_overflow() {
+        let (d, flag) = ExceptionFlags::capture(|| {
+            round_pack::<F16>(false, 16, 1, false, RoundingMode::TiesToEven)
+        });
+        assert_eq!(d.to_bits(), 0x7c00);
+        assert!(flag.is_overflow() && flag.is_inexact());
+        let d = round_pack::<F16>(true, 16, 1, false, RoundingMode::TowardPositive);
+        assert_eq!(d.to_bits(), 0xfbff);
+        // 65520 rounds to infinity
+        let d = round_pack::<F16>(false, 4, 4095, false, RoundingMode::TiesToEven);
+        assert_eq!(d.to_bits(), 0x7c00);
+        let d = round_pack::<F16>(false, 4, 4095, false, RoundingMode::TowardZero);
+        assert_eq!(d.to_bits(), 0x7bff);
+    }
+
+    #[test]
+    fn round_pack_subnormal() {
+        let rnd = RoundingMode::TiesToEven;
+        let (d, flag) = ExceptionFlags::capture(|| round_pack::<F16>(false, -24, 3, false, rnd));
+        assert_eq!(d.to_bits(), 0x0003);
+        assert_eq!(flag.to_bits(), 0x0);
+        let (d, flag) = ExceptionFlags::capture(|| round_pack::<F16>(false, -25, 3, false, rnd));
+        assert_eq!(d.to_bits(), 0x0002);
+        assert!(flag.is_underflow() && flag.is_inexact());
+        let (d, flag) = ExceptionFlags::capture(|| round_pack::<F16>(false, -1000, 1, true, rnd));
+        assert_eq!(d.to_bits(), 0x0000);
+        assert!(flag.is_underflow() && flag.is_inexact());
+        let d = round_pack::<F16>(false, -1000, 1, true, RoundingMode::TowardPositive);
+        assert_eq!(d.to_bits(), 0x0001);
+
+        // the largest subnormal rounds up to the smallest normal
+        let f = |rnd| {
+            ExceptionFlags::capture(|| {
+                round_pack::<F128>(false, -16496, u128::MAX >> 14, false, rnd)
+            })
+        };
+        let (d, flag) = f(rnd);
+        assert_eq!(d.to_bits(), 0x0001 << 112);
+        assert!(!flag.is_underflow());
+        let (d, flag) = f(RoundingMode::TowardZero);
+        assert_eq!(d.to_bits(), (1 << 112) - 1);
+        assert!(flag.is_underflow());
+    }
+
+    #[test]
+    fn round_to_int() {
+        let rnd = RoundingMode::TiesToEven;
+        assert_eq!(round_int(false, -1, 5, rnd), Some((2, true)));
+        assert_eq!(round_int(false, -1, 7, rnd), Some((4, true)));
+        assert_eq!(
+            round_int(true, -1, 5, RoundingMode::TowardNegative),
+            Some((3, true))
+        );
+        assert_eq!(
+            round_int(false, -200, 5, RoundingMode::TowardPositive),
+            Some((1, true))
+        );
+        assert_eq!(round_int(false, -200, 5, rnd), Some((0, true)));
+        assert_eq!(round_int(false, -200, 0, rnd), Some((0, false)));
+        assert_eq!(round_int(false, 3, 5, rnd), Some((40, false)));
+        assert_eq!(round_int(false, 127, 1, rnd), Some((1 << 127, false)));
+        assert_eq!(round_int(false, 127, 2, rnd), None);
+        assert_eq!(round_int(false, -1, u128::MAX, rnd), Some((1 << 127, true)));
+        assert_eq!(
+            round_int(false, 0, u128::MAX, rnd),
+            Some((u128::MAX, false))
+        );
+    }
+}
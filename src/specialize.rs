@@ -0,0 +1,463 @@
+use crate::{Float, Tininess};
+use num_traits::{NumCast, One, PrimInt, ToPrimitive, Zero};
+use std::cell::Cell;
+
+/// SoftFloat specialization
+///
+/// The specialization decides the default NaN, NaN propagation, integer results of invalid conversions
+/// and the default tininess detection mode.
+/// It can be switched at runtime independently of the Cargo feature selecting the compiled one.
+///
+/// ## Examples
+///
+/// ```
+/// use softfloat_wrapper::{Float, RoundingMode, Specialization, F32};
+///
+/// let a = F32::from_bits(0x0);
+/// let d = Specialization::RiscV.scope(|| a.div(a, RoundingMode::TiesToEven));
+/// assert_eq!(d.to_bits(), 0x7fc00000);
+/// let d = Specialization::X86Sse.scope(|| a.div(a, RoundingMode::TiesToEven));
+/// assert_eq!(d.to_bits(), 0xffc00000);
+/// ```
+#[derive(Copy, Clone, Debug, PartialEq, Eq, Hash)]
+pub enum Specialization {
+    /// Intel x86 with x87 (feature `8086`)
+    X86,
+    /// Intel x86 with SSE (feature `8086-sse`)
+    X86Sse,
+    /// ARM VFPv2 (feature `arm-vfpv2`)
+    ArmVfpv2,
+    /// ARM VFPv2 in default NaN mode (feature `arm-vfpv2-defaultnan`)
+    ArmVfpv2DefaultNaN,
+    /// RISC-V (feature `riscv`)
+    RiscV,
+}
+
+thread_local! {
+    static SPECIALIZATION: Cell<Specialization> = Cell::new(Specialization::compiled());
+}
+
+impl Specialization {
+    /// Returns the specialization selected by the Cargo feature
+    pub fn compiled() -> Self {
+        if cfg!(feature = "8086") {
+            Specialization::X86
+        } else if cfg!(feature = "arm-vfpv2") {
+            Specialization::ArmVfpv2
+        } else if cfg!(feature = "arm-vfpv2-defaultnan") {
+            Specialization::ArmVfpv2DefaultNaN
+        } else if cfg!(feature = "riscv") {
+            Specialization::RiscV
+        } else {
+            Specialization::X86Sse
+        }
+    }
+
+    /// Returns the specialization active on the current thread
+    pub fn current() -> Self {
+        SPECIALIZATION.with(|x| x.get())
+    }
+
+    /// Activates the specialization on the current thread
+    ///
+    /// The tininess detection mode is also set to the default of the specialization.
+    pub fn set(&self) {
+        self.store();
+        self.tininess().set();
+    }
+
+    /// Runs `f` with the specialization and restores the previous one afterwards
+    pub fn scope<T, F: FnOnce() -> T>(&self, f: F) -> T {
+        let saved = Saved {
+            spec: Self::current(),
+            tininess: Tininess::current(),
+        };
+        self.set();
+        let ret = f();
+        drop(saved);
+        ret
+    }
+
+    pub(crate) fn store(&self) {
+        SPECIALIZATION.with(|x| x.set(*self));
+    }
+
+    /// Returns the default NaN generated by invalid operations
+    pub fn default_nan<F: Float>(&self) -> F {
+        let bits = (F::EXPONENT_BIT << F::EXPONENT_POS) | quiet_bit::<F>();
+        match self {
+            Specialization::X86 | Specialization::X86Sse => {
+                F::from_bits(bits | (F::Payload::one() << F::SIGN_POS))
+            }
+            _ => F::from_bits(bits),
+        }
+    }
+
+    /// Returns the default tininess detection mode
+    pub fn tininess(&self) -> Tininess {
+        match self {
+            Specialization::ArmVfpv2 | Specialization::ArmVfpv2DefaultNaN => {
+                Tininess::BeforeRounding
+            }
+            _ => Tininess::AfterRounding,
+        }
+    }
+
+    /// Returns the NaN result of an operation with operands `a` and `b`, at least one of which is NaN
+    pub(crate) fn propagate_nan<F: Float>(&self, a: &F, b: &F) -> F {
+        let q = quiet_bit::<F>();
+        let (ua, ub) = (a.to_bits(), b.to_bits());
+        match self {
+            Specialization::X86 => {
+                let (sa, sb) = (is_signaling(a), is_signaling(b));
+                if sa && !sb {
+                    return F::from_bits(if b.is_nan() { ub | q } else { ua | q });
+                }
+                if sb && !sa {
+                    return F::from_bits(if a.is_nan() { ua | q } else { ub | q });
+                }
+                // returns the NaN with the larger significand
+                let mask = !(F::Payload::one() << F::SIGN_POS);
+                let (ma, mb) = (ua & mask, ub & mask);
+                if ma < mb {
+                    F::from_bits(ub | q)
+                } else if mb < ma {
+                    F::from_bits(ua | q)
+                } else {
+                    F::from_bits((ua | q).min(ub | q))
+                }
+            }
+            Specialization::X86Sse => {
+                if a.is_nan() {
+                    F::from_bits(ua | q)
+                } else {
+                    F::from_bits(ub | q)
+                }
+            }
+            Specialization::ArmVfpv2 => {
+                if is_signaling(a) {
+                    F::from_bits(ua | q)
+                } else if is_signaling(b) {
+                    F::from_bits(ub | q)
+                } else if a.is_nan() {
+                    F::from_bits(ua)
+                } else {
+                    F::from_bits(ub)
+                }
+            }
+            _ => self.default_nan(),
+        }
+    }
+
+    /// Returns the NaN result of converting NaN `a` to another format
+    pub(crate) fn convert_nan<F: Float, G: Float>(&self, a: &F) -> G {
+        match self {
+            Specialization::ArmVfpv2DefaultNaN | Specialization::RiscV => self.default_nan(),
+            _ => {
+                // the payload is aligned to the MSB of the fraction
+                let frac = a.fraction().to_u128().unwrap() << (128 - F::EXPONENT_POS);
+                let frac = <G::Payload as NumCast>::from(frac >> (128 - G::EXPONENT_POS)).unwrap();
+                let sign = if a.is_negative() {
+                    G::Payload::one() << G::SIGN_POS
+                } else {
+                    G::Payload::zero()
+                };
+                G::from_bits(sign | (G::EXPONENT_BIT << G::EXPONENT_POS) | quiet_bit::<G>() | frac)
+            }
+        }
+    }
+
+    /// Returns the integer result of an invalid conversion
+    pub(crate) fn invalid_int<I: PrimInt>(&self, nan: bool, negative: bool) -> I {
+        let signed = I::min_value() < I::zero();
+        match self {
+            Specialization::X86 | Specialization::X86Sse => {
+                if signed {
+                    I::min_value()
+                } else {
+                    I::max_value()
+                }
+            }
+            Specialization::ArmVfpv2 | Specialization::ArmVfpv2DefaultNaN => {
+                if nan {
+                    I::zero()
+                } else if negative {
+                    I::min_value()
+                } else {
+                    I::max_value()
+                }
+            }
+            Specialization::RiscV => {
+                if !nan && negative {
+                    I::min_value()
+                } else {
+                    I::max_value()
+                }
+            }
+        }
+    }
+}
+
+impl Default for Specialization {
+    fn default() -> Self {
+        Self::compiled()
+    }
+}
+
+fn quiet_bit<F: Float>() -> F::Payload {
+    F::Payload::one() << (F::EXPONENT_POS - 1)
+}
+
+fn is_signaling<F: Float>(x: &F) -> bool {
+    x.is_nan() && (x.to_bits() & quiet_bit::<F>()) == F::Payload::zero()
+}
+
+// specialization and tininess detection mode restored on drop, even if the closure of `scope` panics
+struct Saved {
+    spec: Specialization,
+    tininess: Tininess,
+}
+
+impl Drop for Saved {
+    fn drop(&mut self) {
+        self.spec.store();
+        self.tininess.set();
+    }
+}
+
+#[cfg(test)]
+mod tests {
+    use super::Specialization;
+    use crate::{ExceptionFlags, Float, RoundingMode, Tininess, BF16, F128, F16, F32, F64};
+
+    const ALL: [Specialization; 5] = [
+        Specialization::X86,
+        Specialization::X86Sse,
+        Specialization::ArmVfpv2,
+        Specialization::ArmVfpv2DefaultNaN,
+        Specialization::RiscV,
+    ];
+
+    #[test]
+    fn specialization_default_nan() {
+        let a = F32::from_bits(0x0);
+        let expected = [0xffc00000, 0xffc00000, 0x7fc00000, 0x7fc00000, 0x7fc00000];
+        for (spec, expected) in ALL.iter().zip(expected.iter()) {
+            let d = spec.scope(|| a.div(a, RoundingMode::TiesToEven));
+            assert_eq!(d.to_bits(), *expected);
+            assert_eq!(spec.default_nan::<F32>().to_bits(), *expected);
+        }
+        let d = Specialization::X86.scope(|| F16::from_bits(0xbc00).sqrt(RoundingMode::TiesToEven));
+        assert_eq!(d.to_bits(), 0xfe00);
+        let d = Specialization::RiscV
+            .scope(|| F64::from_bits(0xbff0000000000000).sqrt(RoundingMode::TiesToEven));
+        assert_eq!(d.to_bits(), 0x7ff8000000000000);
+        let d = Specialization::RiscV
+            .scope(|| F128::from_bits(0xbfff << 112).sqrt(RoundingMode::TiesToEven));
+        assert_eq!(d.to_bits(), 0x7fff8 << 108);
+        let d =
+            Specialization::X86Sse.scope(|| BF16::from_bits(0xbf80).sqrt(RoundingMode::TiesToEven));
+        assert_eq!(d.to_bits(), 0xffc0);
+    }
+
+    #[test]
+    fn specialization_propagate_nan() {
+        // signaling NaN with small payload and quiet NaN with large payload
+        let a = F32::from_bits(0x7f800001);
+        let b = F32::from_bits(0xffc00010);
+        let expected = [0xffc00010, 0x7fc00001, 0x7fc00001, 0x7fc00000, 0x7fc00000];
+        for (spec, expected) in ALL.iter().zip(expected.iter()) {
+            let mut flag = ExceptionFlags::default();
+            flag.set();
+            let d = spec.scope(|| a.add(b, RoundingMode::TiesToEven));
+            flag.get();
+            assert_eq!(d.to_bits(), *expected);
+            assert!(flag.is_invalid());
+        }
+
+        // quiet NaNs only
+        let a = F64::from_bits(0x7ff8000000000001);
+        let b = F64::from_bits(0xfff8000000000010);
+        let expected = [
+            0xfff8000000000010,
+            0x7ff8000000000001,
+            0x7ff8000000000001,
+            0x7ff8000000000000,
+            0x7ff8000000000000,
+        ];
+        for (spec, expected) in ALL.iter().zip(expected.iter()) {
+            let d = spec.scope(|| a.mul(b, RoundingMode::TiesToEven));
+            assert_eq!(d.to_bits(), *expected);
+        }
+
+        // signaling NaN in the second operand
+        let a = F16::from_bits(0x7e01);
+        let b = F16::from_bits(0x7c02);
+        let expected = [0x7e01, 0x7e01, 0x7e02, 0x7e00, 0x7e00];
+        for (spec, expected) in ALL.iter().zip(expected.iter()) {
+            let d = spec.scope(|| a.sub(b, RoundingMode::TiesToEven));
+            assert_eq!(d.to_bits(), *expected);
+        }
+    }
+
+    #[test]
+    fn specialization_fused_mul_add() {
+        // infinity * zero + quiet NaN
+        let a = F32::from_bits(0x7f800000);
+        let b = F32::from_bits(0x0);
+        let c = F32::from_bits(0x7fc00123);
+        let expected = [0x7fc00123, 0xffc00000, 0x7fc00000, 0x7fc00000, 0x7fc00000];
+        for (spec, expected) in ALL.iter().zip(expected.iter()) {
+            let mut flag = ExceptionFlags::default();
+            flag.set();
+            let d = spec.scope(|| a.fused_mul_add(b, c, RoundingMode::TiesToEven));
+            flag.get();
+            assert_eq!(d.to_bits(), *expected);
+            assert!(flag.is_invalid());
+        }
+
+        // quiet NaN * one + signaling NaN
+        let a = F32::from_bits(0x7fc00001);
+        let b = F32::from_bits(0x3f800000);
+        let c = F32::from_bits(0x7f800002);
+        let expected = [0x7fc00001, 0x7fc00001, 0x7fc00002, 0x7fc00000, 0x7fc00000];
+        for (spec, expected) in ALL.iter().zip(expected.iter()) {
+            let d = spec.scope(|| a.fused_mul_add(b, c, RoundingMode::TiesToEven));
+            assert_eq!(d.to_bits(), *expected);
+        }
+    }
+
+    #[test]
+    fn specialization_convert_nan() {
+        let a = F64::from_bits(0xfff4000000000001);
+        let expected = [0xffe00000, 0xffe00000, 0xffe00000, 0x7fc00000, 0x7fc00000];
+        for (spec, expected) in ALL.iter().zip(expected.iter()) {
+            let mut flag = ExceptionFlags::default();
+            flag.set();
+            let d = spec.scope(|| a.to_f32(RoundingMode::TiesToEven));
+            flag.get();
+            assert_eq!(d.to_bits(), *expected);
+            assert!(flag.is_invalid());
+        }
+
+        let a = F16::from_bits(0x7e01);
+        let expected = [
+            0x7fc0_2000,
+            0x7fc0_2000,
+            0x7fc0_2000,
+            0x7fc0_0000,
+            0x7fc0_0000,
+        ];
+        for (spec, expected) in ALL.iter().zip(expected.iter()) {
+            let d = spec.scope(|| a.to_f32(RoundingMode::TiesToEven));
+            assert_eq!(d.to_bits(), *expected);
+        }
+        let expected = [
+            0x7fff << 112 | 0x201 << 102,
+            0x7fff << 112 | 0x201 << 102,
+            0x7fff << 112 | 0x201 << 102,
+            0x7fff8 << 108,
+            0x7fff8 << 108,
+        ];
+        for (spec, expected) in ALL.iter().zip(expected.iter()) {
+            let d = spec.scope(|| a.to_f128(RoundingMode::TiesToEven));
+            assert_eq!(d.to_bits(), *expected);
+        }
+
+        let a = F32::from_bits(0xff812345);
+        let expected = [0xffc1, 0xffc1, 0xffc1, 0x7fc0, 0x7fc0];
+        for (spec, expected) in ALL.iter().zip(expected.iter()) {
+            let d = spec.scope(|| a.to_bf16(RoundingMode::TiesToEven));
+            assert_eq!(d.to_bits(), *expected);
+        }
+    }
+
+    #[test]
+    fn specialization_invalid_int() {
+        let nan = F32::from_bits(0x7fc00000);
+        let pos = F32::from_bits(0x7f000000);
+        let neg = F32::from_bits(0xff000000);
+        let rnd = RoundingMode::TiesToEven;
+        let expected: [(u32, u32, u32); 5] = [
+            (0xffffffff, 0xffffffff, 0xffffffff),
+            (0xffffffff, 0xffffffff, 0xffffffff),
+            (0, 0xffffffff, 0),
+            (0, 0xffffffff, 0),
+            (0xffffffff, 0xffffffff, 0),
+        ];
+        for (spec, expected) in ALL.iter().zip(expected.iter()) {
+            spec.scope(|| {
+                assert_eq!(nan.to_u32(rnd, false), expected.0);
+                assert_eq!(pos.to_u32(rnd, false), expected.1);
+                assert_eq!(neg.to_u32(rnd, false), expected.2);
+            });
+        }
+        let expected: [(i64, i64, i64); 5] = [
+            (i64::MIN, i64::MIN, i64::MIN),
+            (i64::MIN, i64::MIN, i64::MIN),
+            (0, i64::MAX, i64::MIN),
+            (0, i64::MAX, i64::MIN),
+            (i64::MAX, i64::MAX, i64::MIN),
+        ];
+        for (spec, expected) in ALL.iter().zip(expected.iter()) {
+            spec.scope(|| {
+                assert_eq!(nan.to_i64(rnd, false), expected.0);
+                assert_eq!(pos.to_i64(rnd, false), expected.1);
+                assert_eq!(neg.to_i64(rnd, false), expected.2);
+            });
+        }
+
+        // valid conversions are not changed
+        let a = F32::from_bits(0xcf000000);
+        for spec in ALL.iter() {
+            let mut flag = ExceptionFlags::default();
+            flag.set();
+            let d = spec.scope(|| a.to_i32(rnd, false));
+            flag.get();
+            assert_eq!(d, i32::MIN);
+            assert!(!flag.is_invalid());
+        }
+    }
+
+    #[test]
+    fn specialization_tininess() {
+        // (1 - 2^-46) * 2^-126 rounds up to the minimum normal number
+        let a = F32::from_bits(0x00fffffe);
+        let b = F32::from_bits(0x3f000001);
+        for spec in ALL.iter() {
+            let mut flag = ExceptionFlags::default();
+            flag.set();
+            let d = spec.scope(|| a.mul(b, RoundingMode::TiesToEven));
+            flag.get();
+            assert_eq!(d.to_bits(), 0x00800000);
+            assert_eq!(
+                flag.is_underflow(),
+                spec.tininess() == Tininess::BeforeRounding
+            );
+            assert_eq!(spec.scope(Tininess::current), spec.tininess());
+        }
+    }
+
+    #[test]
+    fn specialization_current() {
+        assert_eq!(Specialization::current(), Specialization::compiled());
+        Specialization::RiscV.scope(|| {
+            assert_eq!(Specialization::current(), Specialization::RiscV);
+            Specialization::ArmVfpv2.set();
+            assert_eq!(Specialization::current(), Specialization::ArmVfpv2);
+        });
+        assert_eq!(Specialization::current(), Specialization::compiled());
+    }
+
+    #[test]
+    fn specialization_scope_panic() {
+        let spec = Specialization::current();
+        let tininess = Tininess::current();
+        let ret = std::panic::catch_unwind(|| {
+            Specialization::ArmVfpv2DefaultNaN.scope(|| panic!("in scope"));
+        });
+        assert!(ret.is_err());
+        assert_eq!(Specialization::current(), spec);
+        assert_eq!(Tininess::current(), tininess);
+    }
+}
@@ -0,0 +1,187 @@
+use crate::Float;
+use std::cmp::Ordering;
+use std::hash::{Hash, Hasher};
+
+/// Returns a key whose unsigned order is totalOrder of IEEE 754
+pub(crate) fn key<F: Float + ?Sized>(x: &F) -> u128 {
+    let bits = num_traits::ToPrimitive::to_u128(&x.to_bits()).unwrap();
+    let sign = 1 << F::SIGN_POS;
+    let mask = sign | (sign - 1);
+    if bits & sign != 0 {
+        !bits & mask
+    } else {
+        bits | sign
+    }
+}
+
+/// floating-point value ordered by totalOrder of IEEE 754
+///
+/// NaNs and signed zeros are ordered as
+/// -qNaN < -sNaN < -∞ < ... < -0 < +0 < ... < +∞ < +sNaN < +qNaN,
+/// so it can be sorted and used as keys of `BTreeMap` and `HashMap`.
+///
+/// ## Examples
+///
+/// ```
+/// use softfloat_wrapper::{Float, TotalOrd, F32};
+/// use std::collections::BTreeSet;
+///
+/// let set: BTreeSet<_> = [0x7fc00000, 0x80000000, 0x00000000, 0x7fc00000]
+///     .iter()
+///     .map(|x| TotalOrd::new(F32::from_bits(*x)))
+///     .collect();
+/// let d: Vec<_> = set.iter().map(|x| x.value().to_bits()).collect();
+/// assert_eq!(d, vec![0x80000000, 0x00000000, 0x7fc00000]);
+/// ```
+#[derive(Copy, Clone, Debug)]
+pub struct TotalOrd<F> {
+    value: F,
+}
+
+impl<F: Float> TotalOrd<F> {
+    pub fn new(value: F) -> Self {
+        Self { value }
+    }
+
+    /// Returns the wrapped value
+    pub fn value(&self) -> &F {
+        &self.value
+    }
+
+    /// Returns the wrapped value
+    pub fn into_inner(self) -> F {
+        self.value
+    }
+}
+
+impl<F: Float> From<F> for TotalOrd<F> {
+    fn from(x: F) -> Self {
+        Self::new(x)
+    }
+}
+
+impl<F: Float> PartialEq for TotalOrd<F> {
+    fn eq(&self, x: &Self) -> bool {
+        key(&self.value) == key(&x.value)
+    }
+}
+
+impl<F: Float> Eq for TotalOrd<F> {}
+
+impl<F: Float> PartialOrd for TotalOrd<F> {
+    fn partial_cmp(&self, x: &Self) -> Option<Ordering> {
+        Some(self.cmp(x))
+    }
+}
+
+impl<F: Float> Ord for TotalOrd<F> {
+    fn cmp(&self, x: &Self) -> Ordering {
+        key(&self.value).cmp(&key(&x.value))
+    }
+}
+
+impl<F: Float> Hash for TotalOrd<F> {
+    fn hash<H: Hasher>(&self, state: &mut H) {
+        key(&self.value).hash(state);
+    }
+}
+
+/// slice of floating-point values ordered by totalOrder of IEEE 754
+pub trait TotalOrdSlice {
+    /// Sorts by totalOrder
+    fn sort_total(&mut self);
+
+    /// Moves consecutive values with the same bit pattern to the end, and returns the number of remaining values
+    ///
+    /// ```
+    /// use softfloat_wrapper::{Float, TotalOrdSlice, F16};
+    ///
+    /// let mut x: Vec<_> = [0x3c00, 0x7e00, 0x3c00, 0x8000, 0x7e00]
+    ///     .iter()
+    ///     .map(|x| F16::from_bits(*x))
+    ///     .collect();
+    /// x.sort_total();
+    /// let n = x.dedup_total();
+    /// x.truncate(n);
+    /// let d: Vec<_> = x.iter().map(|x| x.to_bits()).collect();
+    /// assert_eq!(d, vec![0x8000, 0x3c00, 0x7e00]);
+    /// ```
+    fn dedup_total(&mut self) -> usize;
+}
+
+impl<F: Float> TotalOrdSlice for [F] {
+    fn sort_total(&mut self) {
+        self.sort_by_key(key);
+    }
+
+    fn dedup_total(&mut self) -> usize {
+        let mut n = 0;
+        for i in 0..self.len() {
+            if n == 0 || key(&self[i]) != key(&self[n - 1]) {
+                self.swap(n, i);
+                n += 1;
+            }
+        }
+        n
+    }
+}
+
+#[cfg(test)]
+mod tests {
+    use super::{TotalOrd, TotalOrdSlice};
+    use crate::{ExceptionFlags, Float, BF16, F128, F32};
+    use std::collections::HashSet;
+
+    #[test]
+    fn total_order() {
+        let values = [
+            0xffc00001, 0xffc00000, 0xff800001, 0xff800000, 0xbf800000, 0x80000001, 0x80000000,
+            0x00000000, 0x00000001, 0x3f800000, 0x7f800000, 0x7f800001, 0x7fc00000, 0x7fc00001,
+        ];
+        let (_, flag) = ExceptionFlags::capture(|| {
+            for (i, a) in values.iter().enumerate() {
+                for (j, b) in values.iter().enumerate() {
+                    let a = F32::from_bits(*a);
+                    let b = F32::from_bits(*b);
+                    assert_eq!(a.total_order(b), i <= j);
+                    assert_eq!(TotalOrd::new(a).cmp(&TotalOrd::new(b)), i.cmp(&j));
+                }
+            }
+        });
+        assert!(!flag.is_invalid());
+
+        let a = F32::from_bits(0xbf800000);
+        let b = F32::from_bits(0x3f800000);
+        assert!(a.total_order_mag(b) && b.total_order_mag(a));
+        assert!(!F32::from_bits(0xc0000000).total_order_mag(b));
+        assert!(F128::from_bits(1 << 127).total_order(F128::from_bits(0)));
+        assert!(!F128::from_bits(0).total_order(F128::from_bits(1 << 127)));
+    }
+
+    #[test]
+    fn total_ord_hash() {
+        let set: HashSet<_> = [0x7fc0, 0x7fc0, 0x0000, 0x8000, 0x3f80]
+            .iter()
+            .map(|x| TotalOrd::from(BF16::from_bits(*x)))
+            .collect();
+        assert_eq!(set.len(), 4);
+        assert!(set.contains(&TotalOrd::new(BF16::from_bits(0x7fc0))));
+    }
+
+    #[test]
+    fn sort_total() {
+        let mut x: Vec<_> = [
+            0x7fc00000, 0x3f800000, 0x80000000, 0xff800000, 0x00000000, 0xffc00000, 0x3f800000,
+        ]
+        .iter()
+        .map(|x| F32::from_bits(*x))
+        .collect();
+        x.sort_total();
+        let n = x.dedup_total();
+        let d: Vec<_> = x[..n].iter().map(|x| x.to_bits()).collect();
+        assert_eq!(
+            d,
+            vec![0xffc00000, 0xff800000, 0x80000000, 0x00000000, 0x3f800000, 0x7fc00000]
+        );
+    }
+}
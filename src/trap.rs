@@ -0,0 +1,380 @@
+use crate::ExceptionFlags;
+use std::cell::{Cell, RefCell};
+use std::error::Error;
+use std::fmt;
+use std::rc::Rc;
+
+/// operation which raises exceptions
+#[derive(Copy, Clone, Debug, PartialEq, Eq, Hash)]
+pub enum Operation {
+    Add,
+    Sub,
+    Mul,
+    FusedMulAdd,
+    Div,
+    Rem,
+    Sqrt,
+    Compare,
+    RoundToIntegral,
+    /// conversion between floating-point formats
+    Convert,
+    /// conversion from floating-point to integer
+    ToInt,
+    /// conversion from integer to floating-point
+    FromInt,
+    /// `minimum`, `maximum` and their variants
+    MinMax,
+    /// `next_up`, `next_down` and `next_after`
+    NextAfter,
+    Ulp,
+    ScaleB,
+    LogB,
+    Compose,
+    Canonicalize,
+    /// conversion from string
+    Parse,
+}
+
+/// exception raised by an operation with enabled trap
+///
+/// Traps are enabled per thread by `Trap::enable`, and are checked for the operations listed in `Operation`.
+/// Each trapped operation still returns its default result and raises its flags,
+/// and then the handler set by `Trap::set_handler` is called.
+/// `Trap::catch` returns the first trap as an error instead.
+///
+/// ## Examples
+///
+/// ```
+/// use softfloat_wrapper::{ExceptionFlags, Float, Operation, RoundingMode, Trap, F32};
+///
+/// let a = F32::from_bits(0x3f800000);
+/// let b = F32::from_bits(0x00000000);
+/// let err = Trap::catch(ExceptionFlags::INFINITE, || a.div(b, RoundingMode::TiesToEven)).unwrap_err();
+/// assert_eq!(err.operation(), Operation::Div);
+/// assert_eq!(err.operands(), &[0x3f800000, 0x00000000]);
+/// assert!(err.flags().is_infinite());
+/// ```
+#[derive(Clone, Debug)]
+pub struct Trap {
+    operation: Operation,
+    operands: Vec<u128>,
+    flags: ExceptionFlags,
+}
+
+type Handler = Box<dyn FnMut(&Trap)>;
+
+thread_local! {
+    static ENABLED: Cell<u8> = const { Cell::new(0) };
+    static HANDLER: RefCell<Option<Handler>> = RefCell::new(None);
+    // operations called inside a checked operation are not checked separately
+    static ACTIVE: Cell<bool> = const { Cell::new(false) };
+}
+
+impl Trap {
+    /// Returns the operation which raised the exceptions
+    pub fn operation(&self) -> Operation {
+        self.operation
+    }
+
+    /// Returns the bit patterns of operands
+    ///
+    /// Integer operands are sign-extended to 128 bits.
+    pub fn operands(&self) -> &[u128] {
+        &self.operands
+    }
+
+    /// Returns the flags raised by the operation
+    pub fn flags(&self) -> ExceptionFlags {
+        self.flags
+    }
+
+    /// Returns the exceptions whose traps are enabled on the current thread
+    pub fn enabled() -> ExceptionFlags {
+        ExceptionFlags::from_bits(ENABLED.with(|x| x.get()))
+    }
+
+    /// Enables traps of `mask` and disables the others on the current thread
+    pub fn enable(mask: ExceptionFlags) {
+        ENABLED.with(|x| x.set(mask.to_bits()));
+    }
+
+    /// Sets the handler called for each trap on the current thread
+    pub fn set_handler<H: FnMut(&Trap) + 'static>(handler: H) {
+        HANDLER.with(|x| *x.borrow_mut() = Some(Box::new(handler)));
+    }
+
+    /// Removes the handler of the current thread
+    pub fn clear_handler() {
+        HANDLER.with(|x| *x.borrow_mut() = None);
+    }
+
+    /// Runs `f` with traps of `mask` enabled, and returns the first trap raised by it
+    ///
+    /// The enabled traps and the handler of the caller are restored afterwards.
+    pub fn catch<T, F: FnOnce() -> T>(mask: ExceptionFlags, f: F) -> Result<T, Trap> {
+        let caught = Rc::new(RefCell::new(None));
+        let handler: Handler = {
+            let caught = caught.clone();
+            Box::new(move |trap: &Trap| {
+                caught.borrow_mut().get_or_insert_with(|| trap.clone());
+            })
+        };
+        let saved = Saved {
+            enabled: Self::enabled(),
+            handler: HANDLER.with(|x| x.borrow_mut().replace(handler)),
+        };
+        Self::enable(mask);
+        let ret = f();
+        drop(saved);
+        let caught = caught.borrow_mut().take();
+        match caught {
+            Some(trap) => Err(trap),
+            None => Ok(ret),
+        }
+    }
+}
+
+// enabled traps and handler restored on drop, even if the closure panics
+struct Saved {
+    enabled: ExceptionFlags,
+    handler: Option<Handler>,
+}
+
+impl Drop for Saved {
+    fn drop(&mut self) {
+        Trap::enable(self.enabled);
+        let handler = self.handler.take();
+        HANDLER.with(|x| *x.borrow_mut() = handler);
+    }
+}
+
+// state of `check` restored on drop, even if the operation or the handler panics
+struct Active {
+    flags: u8,
+}
+
+impl Drop for Active {
+    fn drop(&mut self) {
+        ACTIVE.with(|x| x.set(false));
+        let op_flags = unsafe { softfloat_sys::softfloat_exceptionFlags_read_helper() };
+        unsafe { softfloat_sys::softfloat_exceptionFlags_write_helper(self.flags | op_flags) };
+    }
+}
+
+// handler taken out while it runs, and put back on drop
+struct Running(Option<Handler>);
+
+impl Drop for Running {
+    fn drop(&mut self) {
+        if let Some(handler) = self.0.take() {
+            HANDLER.with(|x| {
+                let mut x = x.borrow_mut();
+                if x.is_none() {
+                    *x = Some(handler);
+                }
+            });
+        }
+    }
+}
+
+impl fmt::Display for Trap {
+    fn fmt(&self, f: &mut fmt::Formatter) -> fmt::Result {
+        write!(
+            f,
+            "floating-point exception in {:?}: {}",
+            self.operation, self.flags
+        )
+    }
+}
+
+impl Error for Trap {}
+
+/// Runs `op` and delivers a trap if it raises enabled exceptions
+pub(crate) fn check<T, O: FnOnce() -> T>(operation: Operation, operands: &[u128], op: O) -> T {
+    let enabled = ENABLED.with(|x| x.get());
+    if enabled == 0 || ACTIVE.with(|x| x.get()) {
+        return op();
+    }
+    let active = Active {
+        flags: unsafe { softfloat_sys::softfloat_exceptionFlags_read_helper() },
+    };
+    unsafe { softfloat_sys::softfloat_exceptionFlags_write_helper(0) };
+    ACTIVE.with(|x| x.set(true));
+    let ret = op();
+    let op_flags = unsafe { softfloat_sys::softfloat_exceptionFlags_read_helper() };
+    drop(active);
+    if op_flags & enabled != 0 {
+        let trap = Trap {
+            operation,
+            operands: operands.to_vec(),
+            flags: ExceptionFlags::from_bits(op_flags),
+        };
+        // the handler is taken out while it runs, so operations in it are not trapped by itself
+        let mut running = Running(HANDLER.with(|x| x.borrow_mut().take()));
+        if let Some(handler) = running.0.as_mut() {
+            handler(&trap);
+        }
+    }
+    ret
+}
+
+#[cfg(test)]
+mod tests {
+    use super::{Operation, Trap};
+    use crate::{ExceptionFlags, Float, FpEnv, Predicate, RoundingMode, F128, F16, F32, F64};
+    use std::cell::RefCell;
+    use std::panic::{self, AssertUnwindSafe};
+    use std::rc::Rc;
+
+    const INVALID: ExceptionFlags = ExceptionFlags::INVALID;
+    const INEXACT: ExceptionFlags = ExceptionFlags::INEXACT;
+
+    #[test]
+    fn trap_catch() {
+        let rnd = RoundingMode::TiesToEven;
+        let a = F64::from_bits(0x0000000000000000);
+        let (ret, flag) = ExceptionFlags::capture(|| Trap::catch(INVALID, || a.div(a, rnd)));
+        let err = ret.unwrap_err();
+        assert_eq!(err.operation(), Operation::Div);
+        assert_eq!(err.operands(), &[0, 0]);
+        assert!(err.flags().is_invalid());
+        assert!(flag.is_invalid());
+        assert_eq!(err.to_string(), "floating-point exception in Div: NV");
+        assert_eq!(Trap::enabled().to_bits(), 0);
+
+        let b = F64::from_bits(0x3ff0000000000000);
+        let ret = Trap::catch(INVALID, || b.div(b, rnd).sqrt(rnd));
+        assert_eq!(ret.unwrap().to_bits(), b.to_bits());
+
+        let c = F16::from_bits(0x3c01);
+        let err = Trap::catch(INEXACT, || c.fused_mul_add(c, c, rnd)).unwrap_err();
+        assert_eq!(err.operation(), Operation::FusedMulAdd);
+        assert_eq!(err.operands(), &[0x3c01, 0x3c01, 0x3c01]);
+        let err = Trap::catch(INEXACT, || F32::from_i64(-0x1000001, rnd)).unwrap_err();
+        assert_eq!(err.operation(), Operation::FromInt);
+        assert_eq!(err.operands(), &[-0x1000001i128 as u128]);
+        let err = Trap::catch(INEXACT, || F128::from_u128(u128::MAX, rnd)).unwrap_err();
+        assert_eq!(err.operation(), Operation::FromInt);
+        let err =
+            Trap::catch(INVALID, || F32::signaling_nan().lt_quiet(b.to_f32(rnd))).unwrap_err();
+        assert_eq!(err.operation(), Operation::Compare);
+
+        // conversions to narrow integers are trapped once
+        let d = F32::from_bits(0x43800000);
+        let err = Trap::catch(INVALID, || d.to_int::<u8>(rnd, false)).unwrap_err();
+        assert_eq!(err.operation(), Operation::ToInt);
+        assert_eq!(err.operands(), &[0x43800000]);
+        let err = Trap::catch(INEXACT, || d.to_f16(rnd).to_f16(rnd)).err();
+        assert!(err.is_none());
+        let err =
+            Trap::catch(INEXACT, || b.to_f16(rnd).div(F16::from_bits(0x4200), rnd)).unwrap_err();
+        assert_eq!(err.operation(), Operation::Div);
+    }
+
+    #[test]
+    fn trap_handler() {
+        let rnd = RoundingMode::TiesToEven;
+        let traps = Rc::new(RefCell::new(Vec::new()));
+        {
+            let traps = traps.clone();
+            Trap::set_handler(move |x| traps.borrow_mut().push(x.operation()));
+        }
+        let a = F32::from_bits(0x3f800000);
+        let b = F32::from_bits(0x40400000);
+        let d = a.div(b, rnd);
+        assert!(traps.borrow().is_empty());
+
+        Trap::enable(INEXACT);
+        let d = a.div(b, rnd).add(d, rnd).mul(b, rnd);
+        assert_eq!(d.to_bits(), 0x40000000);
+        {
+            let env = FpEnv::hold();
+            let _ = a.div(b, rnd);
+            drop(env);
+        }
+        let _ = a.sub(b, rnd).to_f64(rnd).to_f32(rnd);
+        Trap::enable(ExceptionFlags::default());
+        let _ = a.div(b, rnd);
+        Trap::clear_handler();
+        assert_eq!(*traps.borrow(), vec![Operation::Div, Operation::Mul]);
+    }
+
+    #[test]
+    fn trap_catch_operations() {
+        let rnd = RoundingMode::TiesToEven;
+        let one = F32::from_bits(0x3f800000);
+        let qnan = F32::quiet_nan();
+        let snan = F32::signaling_nan();
+        let big = F32::from_bits(0x7f000000);
+        fn operation<T: std::fmt::Debug>(ret: Result<T, Trap>) -> Operation {
+            ret.unwrap_err().operation()
+        }
+
+        let d = Trap::catch(INVALID, || qnan.compare(one));
+        assert_eq!(operation(d), Operation::Compare);
+        let d = Trap::catch(INVALID, || snan.compare_quiet(one));
+        assert_eq!(operation(d), Operation::Compare);
+        let d = Trap::catch(INVALID, || {
+            qnan.compare_predicate(one, Predicate::SignalingLess)
+        });
+        assert_eq!(operation(d), Operation::Compare);
+        let d = Trap::catch(INVALID, || snan.maximum(one));
+        assert_eq!(operation(d), Operation::MinMax);
+        let d = Trap::catch(INVALID, || one.minimum_number(snan));
+        assert_eq!(operation(d), Operation::MinMax);
+        let d = Trap::catch(INVALID, || snan.next_up());
+        assert_eq!(operation(d), Operation::NextAfter);
+        let d = Trap::catch(INVALID, || snan.ulp());
+        assert_eq!(operation(d), Operation::Ulp);
+        let d = Trap::catch(ExceptionFlags::OVERFLOW, || big.scale_b(10, rnd));
+        assert_eq!(operation(d), Operation::ScaleB);
+        let d = Trap::catch(INVALID, || qnan.log_b());
+        assert_eq!(operation(d), Operation::LogB);
+        let d = Trap::catch(INEXACT, || F32::compose(false, 0, (1 << 24) + 1, rnd));
+        assert_eq!(operation(d), Operation::Compose);
+        let d = Trap::catch(INVALID, || snan.canonicalize());
+        assert_eq!(operation(d), Operation::Canonicalize);
+        let d = Trap::catch(INEXACT, || F32::from_str_rounded("0.1", rnd));
+        assert_eq!(operation(d), Operation::Parse);
+
+        let err = Trap::catch(INVALID, || one.next_after(snan)).unwrap_err();
+        assert_eq!(err.operands(), &[0x3f800000, snan.to_bits() as u128]);
+        assert!(Trap::catch(INVALID, || qnan.compare_quiet(one)).is_ok());
+        assert!(Trap::catch(INVALID, || qnan.maximum(one)).is_ok());
+    }
+
+    #[test]
+    fn trap_panic() {
+        let rnd = RoundingMode::TiesToEven;
+        let a = F64::from_bits(0x0000000000000000);
+        let ret = panic::catch_unwind(|| {
+            Trap::catch(INVALID, || {
+                let _ = a.div(a, rnd);
+                panic!("in catch");
+            })
+        });
+        assert!(ret.is_err());
+        assert_eq!(Trap::enabled().to_bits(), 0);
+
+        let count = Rc::new(RefCell::new(0));
+        {
+            let count = count.clone();
+            Trap::set_handler(move |_| {
+                *count.borrow_mut() += 1;
+                if *count.borrow() == 1 {
+                    panic!("in handler");
+                }
+            });
+        }
+        Trap::enable(INVALID);
+        let ret = panic::catch_unwind(AssertUnwindSafe(|| a.div(a, rnd)));
+        assert!(ret.is_err());
+        let _ = a.div(a, rnd);
+        Trap::enable(ExceptionFlags::default());
+        Trap::clear_handler();
+        assert_eq!(*count.borrow(), 2);
+
+        let ret = Trap::catch(INVALID, || a.div(a, rnd));
+        assert!(ret.is_err());
+    }
+}
@@ -0,0 +1,222 @@
+use crate::{op, Float, RoundingMode};
+use std::cmp::Ordering;
+use std::ops::{
+    Add, AddAssign, Div, DivAssign, Mul, MulAssign, Neg, Rem, RemAssign, Sub, SubAssign,
+};
+
+/// floating-point value with rounding mode for operators
+///
+/// Binary operators use the rounding mode of the left-hand side.
+/// `%` truncates the quotient like primitive floats, so it differs from `Float::rem`.
+/// Comparison operators are quiet, so they don't raise invalid for quiet NaN.
+///
+/// ## Examples
+///
+/// ```
+/// use softfloat_wrapper::{Float, RoundingMode, WithMode, F16};
+///
+/// fn mul_add<T: Float + Copy>(a: T, b: T, c: T) -> T {
+///     let a = WithMode::new(a, RoundingMode::TiesToEven);
+///     (a * b + c).value()
+/// }
+///
+/// let a = F16::from_bits(0x3c00);
+/// let b = F16::from_bits(0x4000);
+/// let c = F16::from_bits(0x3c00);
+/// assert_eq!(mul_add(a, b, c).to_bits(), 0x4200);
+/// ```
+#[derive(Copy, Clone, Debug)]
+pub struct WithMode<F> {
+    value: F,
+    rnd: RoundingMode,
+}
+
+impl<F: Float + Copy> WithMode<F> {
+    /// Creates `WithMode` which rounds the results of operators by `rnd`
+    pub fn new(value: F, rnd: RoundingMode) -> Self {
+        Self { value, rnd }
+    }
+
+    /// Returns the wrapped value
+    pub fn value(&self) -> F {
+        self.value
+    }
+
+    /// Returns the rounding mode used by operators
+    pub fn rounding_mode(&self) -> RoundingMode {
+        self.rnd
+    }
+}
+
+macro_rules! impl_op {
+    ($op:ident, $op_fn:ident, $assign:ident, $assign_fn:ident, $f:path) => {
+        impl<F: Float + Copy> $op for WithMode<F> {
+            type Output = Self;
+
+            fn $op_fn(self, x: Self) -> Self {
+                Self::new($f(&self.value, x.value, self.rnd), self.rnd)
+            }
+        }
+
+        impl<F: Float + Copy> $op<F> for WithMode<F> {
+            type Output = Self;
+
+            fn $op_fn(self, x: F) -> Self {
+                Self::new($f(&self.value, x, self.rnd), self.rnd)
+            }
+        }
+
+        impl<F: Float + Copy> $assign for WithMode<F> {
+            fn $assign_fn(&mut self, x: Self) {
+                self.value = $f(&self.value, x.value, self.rnd);
+            }
+        }
+
+        impl<F: Float + Copy> $assign<F> for WithMode<F> {
+            fn $assign_fn(&mut self, x: F) {
+                self.value = $f(&self.value, x, self.rnd);
+            }
+        }
+    };
+}
+
+fn fmod<F: Float>(a: &F, b: F, _rnd: RoundingMode) -> F {
+    op::fmod(a, &b)
+}
+
+impl_op!(Add, add, AddAssign, add_assign, Float::add);
+impl_op!(Sub, sub, SubAssign, sub_assign, Float::sub);
+impl_op!(Mul, mul, MulAssign, mul_assign, Float::mul);
+impl_op!(Div, div, DivAssign, div_assign, Float::div);
+impl_op!(Rem, rem, RemAssign, rem_assign, fmod);
+
+impl<F: Float + Copy> Neg for WithMode<F> {
+    type Output = Self;
+
+    fn neg(self) -> Self {
+        Self::new(self.value.neg(), self.rnd)
+    }
+}
+
+impl<F: Float + Copy> PartialEq for WithMode<F> {
+    fn eq(&self, x: &Self) -> bool {
+        self.value.eq(x.value)
+    }
+}
+
+impl<F: Float + Copy> PartialOrd for WithMode<F> {
+    fn partial_cmp(&self, x: &Self) -> Option<Ordering> {
+        if self.value.is_nan() || x.value.is_nan() {
+            None
+        } else if self.value.eq(x.value) {
+            Some(Ordering::Equal)
+        } else if self.value.lt_quiet(x.value) {
+            Some(Ordering::Less)
+        } else {
+            Some(Ordering::Greater)
+        }
+    }
+
+    fn lt(&self, x: &Self) -> bool {
+        self.value.lt_quiet(x.value)
+    }
+
+    fn le(&self, x: &Self) -> bool {
+        self.value.le_quiet(x.value)
+    }
+
+    fn gt(&self, x: &Self) -> bool {
+        x.value.lt_quiet(self.value)
+    }
+
+    fn ge(&self, x: &Self) -> bool {
+        x.value.le_quiet(self.value)
+    }
+}
+
+#[cfg(test)]
+mod tests {
+    use super::WithMode;
+    use crate::{ExceptionFlags, Float, RoundingMode, BF16, F128, F32, F64};
+    use std::cmp::Ordering;
+
+    #[test]
+    fn with_mode_ops() {
+        let rnd = RoundingMode::TowardZero;
+        let a = F32::from_bits(0x3f8ccccd);
+        let b = F32::from_bits(0x40066666);
+        let a0 = WithMode::new(a, rnd);
+        let b0 = WithMode::new(b, rnd);
+        assert_eq!((a0 + b0).value().to_bits(), a.add(b, rnd).to_bits());
+        assert_eq!((a0 - b0).value().to_bits(), a.sub(b, rnd).to_bits());
+        assert_eq!((a0 * b0).value().to_bits(), a.mul(b, rnd).to_bits());
+        assert_eq!((a0 / b0).value().to_bits(), a.div(b, rnd).to_bits());
+        assert_eq!((a0 % b0).value().to_bits(), (a % b).to_bits());
+        assert_eq!((a0 * b).value().to_bits(), a.mul(b, rnd).to_bits());
+        assert_eq!((-a0).value().to_bits(), 0xbf8ccccd);
+
+        let mut d = a0;
+        d += b0;
+        d *= b;
+        d -= a0;
+        d /= b0;
+        d %= a;
+        let e = a.add(b, rnd).mul(b, rnd).sub(a, rnd).div(b, rnd) % a;
+        assert_eq!(d.value().to_bits(), e.to_bits());
+        assert_eq!(d.rounding_mode().to_softfloat(), rnd.to_softfloat());
+    }
+
+    #[test]
+    fn with_mode_rem() {
+        // `%` of `WithMode` is the same as the one of the wrapped type
+        let f = |x: f64| F64::from_bits(x.to_bits());
+        for (x, y) in [
+            (5.0, 3.0),
+            (-5.0, 3.0),
+            (5.0, -3.0),
+            (-5.0, -3.0),
+            (7.5, 2.0),
+            (-0.0, 1.0),
+        ] {
+            let d = WithMode::new(f(x), RoundingMode::TowardNegative) % f(y);
+            assert_eq!(d.value().to_bits(), (f(x) % f(y)).to_bits());
+            assert_eq!(d.value().to_bits(), (x % y).to_bits());
+        }
+    }
+
+    #[test]
+    fn with_mode_rounding_mode() {
+        let a = BF16::from_bits(0x3f80);
+        let b = BF16::from_bits(0x3b80);
+        let d = WithMode::new(a, RoundingMode::TowardPositive) + b;
+        assert_eq!(d.value().to_bits(), 0x3f81);
+        let d = WithMode::new(a, RoundingMode::TowardNegative) + b;
+        assert_eq!(d.value().to_bits(), 0x3f80);
+    }
+
+    #[test]
+    fn with_mode_compare() {
+        let rnd = RoundingMode::TiesToEven;
+        let a = WithMode::new(F128::from_bits(0x3fff << 112), rnd);
+        let b = WithMode::new(F128::from_bits(0x4000 << 112), rnd);
+        let nan = WithMode::new(F128::quiet_nan(), rnd);
+        assert!(a < b);
+        assert!(a <= b);
+        assert!(b > a);
+        assert!(b >= a);
+        assert!(a == a);
+        assert!(a != b);
+        assert_eq!(a.partial_cmp(&b), Some(Ordering::Less));
+        assert_eq!(b.partial_cmp(&a), Some(Ordering::Greater));
+        assert_eq!(a.partial_cmp(&a), Some(Ordering::Equal));
+
+        let mut flag = ExceptionFlags::default();
+        flag.set();
+        assert_eq!(a.partial_cmp(&nan), None);
+        let d = [a < nan, a <= nan, a > nan, a >= nan];
+        assert_eq!(d, [false; 4]);
+        assert!(nan != nan);
+        flag.get();
+        assert!(!flag.is_invalid());
+    }
+}